@@ -0,0 +1,357 @@
+/// Helpers for turning a sequence of character grids into terminal output or image files.
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+
+use crate::template::{ANSI_BOLD, ANSI_RESET};
+
+/// A single rendered state, one string per row of the grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub caption: String,
+    pub rows: Vec<String>,
+}
+
+impl Frame {
+    #[must_use]
+    pub fn new(caption: impl Into<String>, rows: Vec<String>) -> Self {
+        Self {
+            caption: caption.into(),
+            rows,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Grid characters in row-major order, padding short rows with spaces.
+    fn cells(&self, width: usize) -> impl Iterator<Item = char> + '_ {
+        self.rows
+            .iter()
+            .flat_map(move |row| row.chars().chain(std::iter::repeat(' ')).take(width))
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.caption)?;
+        for row in &self.rows {
+            writeln!(f, "{row}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Default colour for a grid character when exporting images.
+#[must_use]
+pub fn colour(c: char) -> [u8; 3] {
+    match c {
+        '#' => [96, 96, 96],
+        '.' | ' ' => [16, 16, 16],
+        '@' => [230, 50, 50],
+        'O' | '[' | ']' => [200, 140, 60],
+        _ => [240, 240, 240],
+    }
+}
+
+/// Writes a frame as a binary PPM image, with each grid cell drawn as a `scale`×`scale` square.
+pub fn write_ppm(frame: &Frame, scale: usize, path: &Path) -> io::Result<()> {
+    let width = frame.width();
+    let cells: Vec<char> = frame.cells(width).collect();
+
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(
+        out,
+        "P6\n{} {}\n255\n",
+        width * scale,
+        frame.height() * scale
+    )?;
+
+    for row in cells.chunks(width.max(1)) {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&c| std::iter::repeat_n(colour(c), scale))
+            .flatten()
+            .collect();
+        for _ in 0..scale {
+            out.write_all(&line)?;
+        }
+    }
+
+    out.flush()
+}
+
+/// Writes every frame to `dir` as `frame_0000.ppm`, `frame_0001.ppm`, ...
+/// Frames are written as they are produced, so only one is held in memory at a time.
+pub fn write_ppm_sequence(
+    frames: impl IntoIterator<Item = Frame>,
+    scale: usize,
+    dir: &Path,
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (i, frame) in frames.into_iter().enumerate() {
+        write_ppm(&frame, scale, &dir.join(format!("frame_{i:04}.ppm")))?;
+    }
+    Ok(())
+}
+
+/// Writes the frames as a looping animated GIF, showing each frame for `delay` hundredths of a second.
+///
+/// Frames are encoded as they are produced, so only one is held in memory at a time. The image
+/// takes the size of the first frame; later frames are padded or cropped to it. Each frame has
+/// its own colour table of at most 256 colours. Pixel data is stored without real compression:
+/// the LZW stream is reset often enough that every code stays a 9-bit literal.
+pub fn write_gif(
+    frames: impl IntoIterator<Item = Frame>,
+    scale: usize,
+    delay: u16,
+    path: &Path,
+) -> io::Result<()> {
+    let mut frames = frames.into_iter().peekable();
+    let (width, height) = frames
+        .peek()
+        .map_or((0, 0), |frame| (frame.width(), frame.height()));
+    let (screen_width, screen_height) = (
+        gif_dimension(width * scale)?,
+        gif_dimension(height * scale)?,
+    );
+
+    let mut out = BufWriter::new(File::create(path)?);
+
+    // Header and logical screen descriptor without a global colour table
+    out.write_all(b"GIF89a")?;
+    out.write_all(&screen_width.to_le_bytes())?;
+    out.write_all(&screen_height.to_le_bytes())?;
+    out.write_all(&[0x70, 0, 0])?;
+
+    // Netscape application extension: loop forever
+    out.write_all(&[0x21, 0xFF, 0x0B])?;
+    out.write_all(b"NETSCAPE2.0")?;
+    out.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+
+    for frame in frames {
+        let (mut palette, pixels) = index_pixels(&frame, width, height, scale)?;
+        palette.resize(256, [0, 0, 0]);
+
+        // Graphic control extension with the frame delay
+        out.write_all(&[0x21, 0xF9, 0x04, 0x00])?;
+        out.write_all(&delay.to_le_bytes())?;
+        out.write_all(&[0x00, 0x00])?;
+
+        // Image descriptor covering the whole screen, with a 256-entry local colour table
+        out.write_all(&[0x2C, 0, 0, 0, 0])?;
+        out.write_all(&screen_width.to_le_bytes())?;
+        out.write_all(&screen_height.to_le_bytes())?;
+        out.write_all(&[0x87])?;
+        out.write_all(&palette.concat())?;
+
+        out.write_all(&[8])?;
+        for block in lzw_literals(&pixels).chunks(255) {
+            out.write_all(&[block.len() as u8])?;
+            out.write_all(block)?;
+        }
+        out.write_all(&[0x00])?;
+    }
+
+    out.write_all(&[0x3B])?;
+    out.flush()
+}
+
+/// The colours of a frame and its scaled pixels as indices into them, `width`×`height` cells large.
+fn index_pixels(
+    frame: &Frame,
+    width: usize,
+    height: usize,
+    scale: usize,
+) -> io::Result<(Vec<[u8; 3]>, Vec<u8>)> {
+    let cells: Vec<char> = frame
+        .cells(width)
+        .chain(std::iter::repeat(' '))
+        .take(width * height)
+        .collect();
+
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut pixels = Vec::with_capacity(width * height * scale * scale);
+    for row in cells.chunks(width.max(1)) {
+        let mut line = Vec::with_capacity(width * scale);
+        for &c in row {
+            let rgb = colour(c);
+            let index = match palette.iter().position(|&p| p == rgb) {
+                Some(index) => index,
+                None => {
+                    palette.push(rgb);
+                    palette.len() - 1
+                }
+            };
+            line.extend(std::iter::repeat_n(index as u8, scale));
+        }
+        for _ in 0..scale {
+            pixels.extend_from_slice(&line);
+        }
+    }
+
+    if palette.len() > 256 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "GIF export supports at most 256 colours per frame",
+        ));
+    }
+    Ok((palette, pixels))
+}
+
+fn gif_dimension(len: usize) -> io::Result<u16> {
+    u16::try_from(len).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame is too large for a GIF image",
+        )
+    })
+}
+
+/// Encodes 8-bit indices as an LZW stream of 9-bit literal codes, clearing the code table before
+/// the decoder would switch to 10-bit codes.
+fn lzw_literals(pixels: &[u8]) -> Vec<u8> {
+    const CLEAR: u32 = 256;
+    const END: u32 = 257;
+    const CODES_PER_CLEAR: usize = 253;
+
+    let mut bytes = Vec::with_capacity(pixels.len() * 9 / 8 + 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    let mut emit = |code: u32| {
+        buffer |= code << bits;
+        bits += 9;
+        while bits >= 8 {
+            bytes.push(buffer as u8);
+            buffer >>= 8;
+            bits -= 8;
+        }
+    };
+
+    for chunk in pixels.chunks(CODES_PER_CLEAR) {
+        emit(CLEAR);
+        for &pixel in chunk {
+            emit(u32::from(pixel));
+        }
+    }
+    emit(END);
+    if bits > 0 {
+        bytes.push(buffer as u8);
+    }
+
+    bytes
+}
+
+/// Steps through `count` frames interactively on the terminal, rendering each one on demand.
+///
+/// Reads commands from stdin: enter or `n` for the next frame, `p` for the previous one, a frame
+/// number to jump to it, and `q` to quit.
+pub fn step_through(count: usize, frame: impl Fn(usize) -> Frame) -> io::Result<()> {
+    if count == 0 {
+        return Ok(());
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut current = 0;
+
+    loop {
+        println!("{}", frame(current));
+        print!(
+            "{ANSI_BOLD}[{}/{}]{ANSI_RESET} (n)ext, (p)rev, <number>, (q)uit > ",
+            current,
+            count - 1
+        );
+        io::stdout().flush()?;
+
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(());
+        };
+
+        match line.trim() {
+            "" | "n" => current = (current + 1).min(count - 1),
+            "p" => current = current.saturating_sub(1),
+            "q" => return Ok(()),
+            other => match other.parse::<usize>() {
+                Ok(index) => current = index.min(count - 1),
+                Err(_) => eprintln!("Unknown command: {other}"),
+            },
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{index_pixels, lzw_literals, write_gif, write_ppm, Frame};
+    use std::{env, fs};
+
+    fn frames() -> Vec<Frame> {
+        vec![
+            Frame::new("first", vec!["#.@".into(), "#O".into()]),
+            Frame::new("second", vec!["#@.".into(), "#O.".into()]),
+        ]
+    }
+
+    #[test]
+    fn renders_text_frames() {
+        assert_eq!(frames()[0].to_string(), "first\n#.@\n#O\n");
+    }
+
+    #[test]
+    fn writes_ppm_with_scaled_dimensions() {
+        let path = env::temp_dir().join("aoc_animation_test.ppm");
+        write_ppm(&frames()[0], 2, &path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        let header = b"P6\n6 4\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(bytes.len(), header.len() + 6 * 4 * 3);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn writes_gif_structure() {
+        let path = env::temp_dir().join("aoc_animation_test.gif");
+        write_gif(frames(), 1, 10, &path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[..6], b"GIF89a");
+        assert_eq!(&bytes[6..10], &[3, 0, 2, 0]);
+        // one image descriptor with a local colour table per frame
+        assert_eq!(bytes.windows(2).filter(|w| w == &[0x00, 0x87]).count(), 2);
+        assert_eq!(bytes.last(), Some(&0x3B));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn pads_and_crops_frames_to_first() {
+        let frame = Frame::new("", vec!["#.@@".into()]);
+        let (palette, pixels) = index_pixels(&frame, 3, 2, 1).unwrap();
+        assert_eq!(palette.len(), 3);
+        assert_eq!(pixels, [0, 1, 2, 1, 1, 1]);
+    }
+
+    #[test]
+    fn packs_lzw_codes_lsb_first() {
+        // clear (256), 0, 1, end (257) as 9-bit codes
+        let bytes = lzw_literals(&[0, 1]);
+        let mut value = 0u64;
+        for (i, &b) in bytes.iter().enumerate() {
+            value |= u64::from(b) << (8 * i);
+        }
+        let codes: Vec<u64> = (0..4).map(|i| (value >> (9 * i)) & 0x1FF).collect();
+        assert_eq!(codes, vec![256, 0, 1, 257]);
+    }
+}
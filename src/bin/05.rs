@@ -1,3 +1,7 @@
+use itertools::Itertools;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
 advent_of_code::solution!(5);
 
 struct PageOrderingRule {
@@ -9,17 +13,6 @@ impl PageOrderingRule {
     fn new(rule1: u32, rule2: u32) -> Self {
         Self { rule1, rule2 }
     }
-
-    fn is_valid(&self, update: &Update) -> bool {
-        if !update.contains(&self.rule1) || !update.contains(&self.rule2) {
-            return true;
-        }
-
-        let rule1_position = update.iter().position(|&x| x == self.rule1).unwrap();
-        let rule2_position = update.iter().position(|&x| x == self.rule2).unwrap();
-
-        rule1_position < rule2_position
-    }
 }
 
 type Update = Vec<u32>;
@@ -29,25 +22,153 @@ fn mid(update: &Update) -> u32 {
     update[mid]
 }
 
-fn reorder_update(update: &[u32], rules: &[PageOrderingRule]) -> Update {
-    let mut new_update = update.to_vec();
+/// Rules that contradict each other within an update, as the pages of the cycle in rule order:
+/// each page must come before the next, and the last before the first.
+#[derive(Debug, PartialEq, Eq)]
+struct RuleCycle {
+    pages: Vec<u32>,
+}
+
+impl fmt::Display for RuleCycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules = self
+            .pages
+            .iter()
+            .zip(self.pages.iter().cycle().skip(1))
+            .map(|(before, after)| format!("{before}|{after}"))
+            .join(", ");
+        write!(f, "contradictory rules {rules}")
+    }
+}
+
+/// Why the pages of an update cannot be put in order.
+#[derive(Debug, PartialEq, Eq)]
+enum SortError {
+    RepeatedPage(u32),
+    Cycle(RuleCycle),
+}
+
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortError::RepeatedPage(page) => write!(f, "page {page} appears more than once"),
+            SortError::Cycle(cycle) => cycle.fmt(f),
+        }
+    }
+}
+
+/// The page ordering rules as a graph, mapping each page to the pages that must come after it.
+///
+/// The full rule set may well contain cycles; only the rules between pages of a single update
+/// have to be consistent.
+struct PrecedenceGraph {
+    successors: HashMap<u32, Vec<u32>>,
+}
+
+impl PrecedenceGraph {
+    fn new(rules: &[PageOrderingRule]) -> Self {
+        let mut successors: HashMap<u32, Vec<u32>> = HashMap::new();
+        for rule in rules {
+            successors.entry(rule.rule1).or_default().push(rule.rule2);
+        }
+
+        Self { successors }
+    }
+
+    fn successors(&self, page: u32) -> &[u32] {
+        self.successors.get(&page).map_or(&[], Vec::as_slice)
+    }
+
+    fn is_valid(&self, update: &[u32]) -> bool {
+        // Earliest position of each page, so a repeated page may not come after any successor
+        let rank: HashMap<u32, usize> = update
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, &p)| (p, i))
+            .collect();
+
+        update.iter().enumerate().all(|(i, &page)| {
+            self.successors(page)
+                .iter()
+                .all(|after| rank.get(after).is_none_or(|&j| i < j))
+        })
+    }
+
+    /// Orders the pages of an update by the rules between them (Kahn's algorithm), keeping pages
+    /// in their original order where the rules allow either.
+    fn sort(&self, update: &[u32]) -> Result<Update, SortError> {
+        if let Some(&page) = update.iter().duplicates().next() {
+            return Err(SortError::RepeatedPage(page));
+        }
+
+        let mut in_degree: HashMap<u32, usize> = update.iter().map(|&p| (p, 0)).collect();
+        for &page in update {
+            for after in self.successors(page) {
+                if let Some(degree) = in_degree.get_mut(after) {
+                    *degree += 1;
+                }
+            }
+        }
+
+        let mut ready: VecDeque<u32> = update
+            .iter()
+            .copied()
+            .filter(|page| in_degree[page] == 0)
+            .collect();
+        let mut sorted = Vec::with_capacity(update.len());
 
-    for rule in rules {
-        if !rule.is_valid(&new_update) {
-            let rule1_position = new_update.iter().position(|&x| x == rule.rule1).unwrap();
-            let rule2_position = new_update.iter().position(|&x| x == rule.rule2).unwrap();
+        while let Some(page) = ready.pop_front() {
+            sorted.push(page);
+            for after in self.successors(page) {
+                if let Some(degree) = in_degree.get_mut(after) {
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push_back(*after);
+                    }
+                }
+            }
+        }
 
-            new_update.swap(rule1_position, rule2_position);
+        if sorted.len() == update.len() {
+            Ok(sorted)
+        } else {
+            let remaining: Vec<u32> = update
+                .iter()
+                .copied()
+                .filter(|page| in_degree[page] > 0)
+                .collect();
+            Err(SortError::Cycle(self.find_cycle(&remaining)))
         }
     }
 
-    new_update
+    /// Finds a cycle among pages left over by the topological sort. Each of them still has a
+    /// predecessor among the others, so walking predecessors must eventually revisit a page.
+    /// Pages must be distinct, or a short sort leaves none of them behind.
+    fn find_cycle(&self, remaining: &[u32]) -> RuleCycle {
+        let mut walk = vec![remaining[0]];
+
+        loop {
+            let page = *walk.last().unwrap();
+            let before = *remaining
+                .iter()
+                .find(|&&p| self.successors(p).contains(&page))
+                .unwrap();
+
+            if let Some(start) = walk.iter().position(|&p| p == before) {
+                let mut pages = walk.split_off(start);
+                pages.reverse();
+                return RuleCycle { pages };
+            }
+            walk.push(before);
+        }
+    }
 }
 
 fn parse(input: &str) -> (Vec<PageOrderingRule>, Vec<Update>) {
     let (rules_input, updates_input) = input.split_once("\n\n").unwrap();
 
-    let mut rules = rules_input
+    let rules = rules_input
         .lines()
         .map(|line| {
             let (rule1, rule2) = line.split_once("|").unwrap();
@@ -55,8 +176,6 @@ fn parse(input: &str) -> (Vec<PageOrderingRule>, Vec<Update>) {
         })
         .collect::<Vec<_>>();
 
-    rules.sort_by(|a, b| a.rule1.cmp(&b.rule1));
-
     let updates: Vec<Update> = updates_input
         .lines()
         .map(|line| {
@@ -71,10 +190,11 @@ fn parse(input: &str) -> (Vec<PageOrderingRule>, Vec<Update>) {
 
 pub fn part_one(input: &str) -> Option<u32> {
     let (rules, updates) = parse(input);
+    let graph = PrecedenceGraph::new(&rules);
 
     let result = updates
         .iter()
-        .filter(|update| rules.iter().all(|rule| rule.is_valid(update)))
+        .filter(|update| graph.is_valid(update))
         .map(mid)
         .sum();
 
@@ -83,14 +203,19 @@ pub fn part_one(input: &str) -> Option<u32> {
 
 pub fn part_two(input: &str) -> Option<u32> {
     let (rules, updates) = parse(input);
+    let graph = PrecedenceGraph::new(&rules);
 
     // Reorder and calculate the mid values of invalid updates
-    let result: u32 = updates
-        .iter()
-        .filter(|update| rules.iter().any(|rule| !rule.is_valid(update)))
-        .map(|update| reorder_update(update, &rules))
-        .map(|reordered| mid(&reordered))
-        .sum();
+    let mut result = 0;
+    for update in updates.iter().filter(|update| !graph.is_valid(update)) {
+        match graph.sort(update) {
+            Ok(reordered) => result += mid(&reordered),
+            Err(err) => {
+                eprintln!("Cannot reorder update {update:?}: {err}");
+                return None;
+            }
+        }
+    }
 
     Some(result)
 }
@@ -110,4 +235,65 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(123));
     }
+
+    #[test]
+    fn test_sort_matches_rules() {
+        let (rules, updates) = parse(&advent_of_code::template::read_file("examples", DAY));
+        let graph = PrecedenceGraph::new(&rules);
+
+        assert_eq!(graph.sort(&updates[3]), Ok(vec![97, 75, 47, 61, 53]));
+        assert_eq!(graph.sort(&updates[4]), Ok(vec![61, 29, 13]));
+        assert_eq!(graph.sort(&updates[5]), Ok(vec![97, 75, 47, 29, 13]));
+
+        for update in &updates {
+            assert!(graph.is_valid(&graph.sort(update).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_sort_needs_more_than_one_pass() {
+        // A single pass of pairwise swaps leaves this reversed chain unsorted
+        let (rules, _) = parse("1|2\n2|3\n3|4\n4|5\n\n1,2");
+        let graph = PrecedenceGraph::new(&rules);
+
+        assert_eq!(graph.sort(&[5, 4, 3, 2, 1]), Ok(vec![1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn test_contradictory_rules() {
+        let input = "1|2\n2|3\n3|1\n3|4\n\n4,3,2,1\n1,2\n";
+        let (rules, updates) = parse(input);
+        let graph = PrecedenceGraph::new(&rules);
+
+        // Only 1, 2 and 3 form the cycle; it does not matter where it is entered
+        let Err(SortError::Cycle(cycle)) = graph.sort(&updates[0]) else {
+            panic!("expected a cycle");
+        };
+        let start = cycle.pages.iter().position(|&p| p == 1).unwrap();
+        let mut pages = cycle.pages.clone();
+        pages.rotate_left(start);
+        assert_eq!(pages, vec![1, 2, 3]);
+        assert_eq!(
+            RuleCycle { pages }.to_string(),
+            "contradictory rules 1|2, 2|3, 3|1"
+        );
+
+        // Updates that leave out part of the cycle are still fine
+        assert_eq!(graph.sort(&updates[1]), Ok(vec![1, 2]));
+        assert_eq!(part_two(input), None);
+    }
+
+    #[test]
+    fn test_repeated_page() {
+        // Sorting [2, 1, 2] used to come up one page short with nothing left to find a cycle in
+        let input = "1|2\n\n2,1,2\n";
+        let (rules, updates) = parse(input);
+        let graph = PrecedenceGraph::new(&rules);
+
+        assert!(!graph.is_valid(&updates[0]));
+        let err = graph.sort(&updates[0]).unwrap_err();
+        assert_eq!(err, SortError::RepeatedPage(2));
+        assert_eq!(err.to_string(), "page 2 appears more than once");
+        assert_eq!(part_two(input), None);
+    }
 }
@@ -1,14 +1,7 @@
 use glam::IVec2;
 use rayon::prelude::*;
-use std::collections::{HashMap, HashSet};
 
-advent_of_code::solution!(6);
-
-#[derive(Debug, Clone)]
-enum PositionType {
-    Empty,
-    Obstacle,
-}
+advent_of_code::solution!(6, parallel);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Facing {
@@ -23,6 +16,8 @@ const RIGHT: IVec2 = IVec2::new(1, 0);
 const DOWN: IVec2 = IVec2::new(0, 1);
 const LEFT: IVec2 = IVec2::new(-1, 0);
 
+const FACINGS: [Facing; 4] = [Facing::Up, Facing::Right, Facing::Down, Facing::Left];
+
 impl Facing {
     fn to_vec2(self) -> IVec2 {
         match self {
@@ -52,115 +47,212 @@ impl Facing {
     }
 }
 
-fn run_guard_simulation(
-    grid: &HashMap<IVec2, PositionType>,
-    guard_starting_position: IVec2,
-    guard_starting_facing: Facing,
-) -> Option<Vec<(IVec2, Facing)>> {
-    use std::collections::HashSet;
-
-    let mut visited: HashSet<(IVec2, Facing)> = HashSet::new();
-    let mut path: Vec<(IVec2, Facing)> = Vec::with_capacity(128); // Preallocate to minimize reallocations
-    let mut guard_pos = guard_starting_position;
-    let mut guard_facing = guard_starting_facing;
-
-    // Insert the starting position
-    visited.insert((guard_pos, guard_facing));
-    path.push((guard_pos, guard_facing));
-
-    loop {
-        // Compute the next position
-        let next_pos = guard_pos + guard_facing.to_vec2();
-
-        match grid.get(&next_pos) {
-            Some(PositionType::Empty) => {
-                // Early check to see if visited already, avoid double lookup
-                if !visited.insert((next_pos, guard_facing)) {
-                    // Loop detected
-                    return None;
-                }
+/// Marks a jump that walks off the edge of the lab.
+const EXIT: u32 = u32::MAX;
+
+/// The lab as a dense grid, with a precomputed jump table giving for every cell and facing the
+/// cell the guard stops on before the next obstacle (or [`EXIT`] if there is none).
+struct Lab {
+    width: i32,
+    height: i32,
+    obstacles: Vec<bool>,
+    jumps: Vec<[u32; 4]>,
+}
+
+impl Lab {
+    fn new(width: i32, height: i32, obstacles: Vec<bool>) -> Self {
+        let mut lab = Self {
+            width,
+            height,
+            obstacles,
+            jumps: vec![[EXIT; 4]; (width * height) as usize],
+        };
+
+        let cells = (0..height).flat_map(|y| (0..width).map(move |x| IVec2::new(x, y)));
+        let cells: Vec<IVec2> = cells.collect();
+
+        for facing in FACINGS {
+            // Visit cells so that the neighbour ahead has always been resolved already
+            let ordered: Box<dyn Iterator<Item = &IVec2>> = match facing {
+                Facing::Up | Facing::Left => Box::new(cells.iter()),
+                Facing::Down | Facing::Right => Box::new(cells.iter().rev()),
+            };
 
-                // Move guard
-                guard_pos = next_pos;
-                path.push((guard_pos, guard_facing));
+            for &pos in ordered {
+                let ahead = pos + facing.to_vec2();
+                let jump = match lab.index(ahead) {
+                    None => EXIT,
+                    Some(i) if lab.obstacles[i] => lab.index(pos).unwrap() as u32,
+                    Some(i) => lab.jumps[i][facing as usize],
+                };
+                let i = lab.index(pos).unwrap();
+                lab.jumps[i][facing as usize] = jump;
             }
-            Some(PositionType::Obstacle) => {
-                // Rotate to the right only if blocked
-                guard_facing = guard_facing.to_facing('R');
+        }
+
+        lab
+    }
+
+    fn index(&self, pos: IVec2) -> Option<usize> {
+        (pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height)
+            .then(|| (pos.y * self.width + pos.x) as usize)
+    }
+
+    fn position(&self, index: u32) -> IVec2 {
+        IVec2::new(index as i32 % self.width, index as i32 / self.width)
+    }
+
+    fn is_obstacle(&self, pos: IVec2) -> Option<bool> {
+        self.index(pos).map(|i| self.obstacles[i])
+    }
+
+    /// Walks the guard one cell at a time, returning every position and facing until it leaves
+    /// the lab, or `None` if it ends up in a loop.
+    fn walk(&self, start: IVec2, facing: Facing) -> Option<Vec<(IVec2, Facing)>> {
+        let mut seen = vec![0u8; self.obstacles.len()];
+        let mut path = Vec::with_capacity(128);
+        let mut guard_pos = start;
+        let mut guard_facing = facing;
+
+        loop {
+            let bit = 1 << guard_facing as u8;
+            let i = self.index(guard_pos).unwrap();
+            if seen[i] & bit != 0 {
+                return None;
             }
-            None => {
-                // Out of bounds
-                break;
+            seen[i] |= bit;
+            path.push((guard_pos, guard_facing));
+
+            loop {
+                let next_pos = guard_pos + guard_facing.to_vec2();
+                match self.is_obstacle(next_pos) {
+                    Some(true) => guard_facing = guard_facing.to_facing('R'),
+                    Some(false) => {
+                        guard_pos = next_pos;
+                        break;
+                    }
+                    None => return Some(path),
+                }
             }
         }
     }
 
-    Some(path)
+    /// Checks whether placing an extra obstacle at `block` traps the guard in a loop.
+    ///
+    /// The guard jumps straight from turn to turn using the jump table; only the added obstacle
+    /// needs special handling, when it lies between the guard and the next stop. `seen` holds one
+    /// stamp per cell and facing, and `stamp` must be unique per call so no clearing is needed.
+    fn loops_with_obstacle(
+        &self,
+        start: IVec2,
+        facing: Facing,
+        block: IVec2,
+        seen: &mut [u32],
+        stamp: u32,
+    ) -> bool {
+        let mut guard_pos = start;
+        let mut guard_facing = facing;
+
+        loop {
+            let dir = guard_facing.to_vec2();
+            let jump = self.jumps[self.index(guard_pos).unwrap()][guard_facing as usize];
+
+            let to_block = block - guard_pos;
+            let block_ahead = to_block.dot(dir) > 0 && to_block.perp_dot(dir) == 0;
+            let stop = if block_ahead
+                && (jump == EXIT || to_block.dot(dir) <= (self.position(jump) - guard_pos).dot(dir))
+            {
+                block - dir
+            } else if jump == EXIT {
+                return false;
+            } else {
+                self.position(jump)
+            };
+
+            let state = self.index(stop).unwrap() * 4 + guard_facing as usize;
+            if seen[state] == stamp {
+                return true;
+            }
+            seen[state] = stamp;
+
+            guard_pos = stop;
+            guard_facing = guard_facing.to_facing('R');
+        }
+    }
 }
 
-fn parse_grid(input: &str) -> (HashMap<IVec2, PositionType>, IVec2, Facing) {
-    // parse the input as a 2D grid stored in a HashMap of Vec2 and PositionType
-    let mut grid = HashMap::new();
+fn parse_grid(input: &str) -> (Lab, IVec2, Facing) {
+    let mut obstacles = Vec::new();
     let mut guard_pos = IVec2::new(0, 0);
     let guard_facing = Facing::Up;
+    let mut width = 0;
+    let mut height = 0;
 
     for (y, line) in input.lines().enumerate() {
+        width = line.len() as i32;
+        height += 1;
+
         for (x, c) in line.chars().enumerate() {
-            let pos = IVec2::new(x as i32, y as i32);
-            let pos_type = match c {
-                '.' => PositionType::Empty,
-                '^' => PositionType::Empty,
-                '#' => PositionType::Obstacle,
+            let is_obstacle = match c {
+                '.' => false,
+                '^' => false,
+                '#' => true,
                 _ => panic!("Invalid character in input"),
             };
 
             if c == '^' {
-                guard_pos = pos;
+                guard_pos = IVec2::new(x as i32, y as i32);
             }
 
-            grid.insert(pos, pos_type);
+            obstacles.push(is_obstacle);
         }
     }
 
-    (grid, guard_pos, guard_facing)
+    (Lab::new(width, height, obstacles), guard_pos, guard_facing)
+}
+
+fn visited_positions(lab: &Lab, guard_pos: IVec2, guard_facing: Facing) -> Option<Vec<IVec2>> {
+    let path = lab.walk(guard_pos, guard_facing)?;
+
+    let mut visited = vec![false; lab.obstacles.len()];
+    let positions = path
+        .into_iter()
+        .filter_map(|(pos, _)| {
+            let i = lab.index(pos).unwrap();
+            (!visited[i]).then(|| {
+                visited[i] = true;
+                pos
+            })
+        })
+        .collect();
+
+    Some(positions)
 }
 
 pub fn part_one(input: &str) -> Option<u32> {
-    let (grid, guard_pos, guard_facing) = parse_grid(input);
+    let (lab, guard_pos, guard_facing) = parse_grid(input);
 
-    let visited = run_guard_simulation(&grid, guard_pos, guard_facing)?;
-    let unique_positions: HashSet<IVec2> = visited.iter().map(|(pos, _)| pos).cloned().collect();
+    let unique_positions = visited_positions(&lab, guard_pos, guard_facing)?;
 
     Some(unique_positions.len() as u32)
 }
 
 pub fn part_two(input: &str) -> Option<u32> {
-    let (grid, guard_pos, guard_facing) = parse_grid(input);
+    let (lab, guard_pos, guard_facing) = parse_grid(input);
 
-    let visited = run_guard_simulation(&grid, guard_pos, guard_facing)?;
-    let unique_positions: HashSet<IVec2> = visited
-        .iter()
-        .filter_map(|(pos, _)| {
-            if *pos == guard_pos {
-                return None;
-            }
-            Some(*pos)
-        })
-        .collect();
+    let unique_positions = visited_positions(&lab, guard_pos, guard_facing)?;
 
     let found = unique_positions
         .par_iter()
-        .filter_map(|pos| {
-            let mut new_grid = grid.clone();
-            new_grid.insert(*pos, PositionType::Obstacle);
-
-            let result = run_guard_simulation(&new_grid, guard_pos, guard_facing);
-            if result.is_none() {
-                Some(*pos)
-            } else {
-                None
-            }
-        })
+        .filter(|&&pos| pos != guard_pos)
+        .map_init(
+            || (vec![0u32; lab.obstacles.len() * 4], 0),
+            |(seen, stamp), &pos| {
+                *stamp += 1;
+                lab.loops_with_obstacle(guard_pos, guard_facing, pos, seen, *stamp)
+            },
+        )
+        .filter(|&loops| loops)
         .count();
 
     Some(found as u32)
@@ -169,6 +261,9 @@ pub fn part_two(input: &str) -> Option<u32> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code::random::Lcg;
+    use std::collections::HashMap;
+    use std::time::Instant;
 
     #[test]
     fn test_part_one() {
@@ -181,4 +276,101 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(6));
     }
+
+    /// The original step-by-step simulation over a `HashMap` grid, kept as a reference.
+    fn naive_part_two(input: &str) -> u32 {
+        let (lab, guard_pos, guard_facing) = parse_grid(input);
+        let grid: HashMap<IVec2, bool> = (0..lab.obstacles.len() as u32)
+            .map(|i| (lab.position(i), lab.obstacles[i as usize]))
+            .collect();
+
+        let loops = |grid: &HashMap<IVec2, bool>| {
+            let mut visited = std::collections::HashSet::new();
+            let mut pos = guard_pos;
+            let mut facing = guard_facing;
+            loop {
+                let next_pos = pos + facing.to_vec2();
+                match grid.get(&next_pos) {
+                    Some(false) => {
+                        if !visited.insert((next_pos, facing)) {
+                            return true;
+                        }
+                        pos = next_pos;
+                    }
+                    Some(true) => facing = facing.to_facing('R'),
+                    None => return false,
+                }
+            }
+        };
+
+        visited_positions(&lab, guard_pos, guard_facing)
+            .unwrap()
+            .into_par_iter()
+            .filter(|&pos| pos != guard_pos)
+            .filter(|pos| {
+                let mut new_grid = grid.clone();
+                new_grid.insert(*pos, true);
+                loops(&new_grid)
+            })
+            .count() as u32
+    }
+
+    /// A deterministic pseudo-random lab with roughly one obstacle in `density` cells, which the
+    /// guard leaves without obstacles being added.
+    fn generate_lab(size: usize, density: u64) -> String {
+        (0..)
+            .map(|seed| generate_lab_from_seed(size, density, seed))
+            .find(|input| {
+                let (lab, guard_pos, guard_facing) = parse_grid(input);
+                lab.walk(guard_pos, guard_facing).is_some()
+            })
+            .unwrap()
+    }
+
+    fn generate_lab_from_seed(size: usize, density: u64, seed: u64) -> String {
+        let mut rng = Lcg::new(seed);
+        let mut lab = String::new();
+        for y in 0..size {
+            for x in 0..size {
+                let obstacle = rng.below(density) == 0;
+                if (x, y) == (size / 2, size / 2) {
+                    lab.push('^');
+                } else if obstacle {
+                    lab.push('#');
+                } else {
+                    lab.push('.');
+                }
+            }
+            lab.push('\n');
+        }
+        lab
+    }
+
+    #[test]
+    fn test_part_two_matches_naive() {
+        for size in [8, 16, 32] {
+            let input = generate_lab(size, 8);
+            assert_eq!(part_two(&input), Some(naive_part_two(&input)));
+        }
+    }
+
+    /// Times the jump table against re-walking the full lab for every candidate obstacle on a
+    /// puzzle-sized grid. Only meaningful in release mode, hence `#[ignore]`.
+    #[test]
+    #[ignore]
+    fn bench_part_two_against_naive() {
+        let input = generate_lab(130, 40);
+
+        let timer = Instant::now();
+        let expected = naive_part_two(&input);
+        let naive = timer.elapsed();
+
+        let timer = Instant::now();
+        let result = part_two(&input);
+        let jumps = timer.elapsed();
+
+        println!("naive: {naive:.1?}, jump table: {jumps:.1?}");
+        assert_eq!(result, Some(expected));
+        assert!(jumps < naive);
+    }
 }
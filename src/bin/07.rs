@@ -1,80 +1,233 @@
 use rayon::prelude::*;
 
-advent_of_code::solution!(7);
+advent_of_code::solution!(7, parallel);
 
-fn apply(op: char, a: u64, b: u64) -> u64 {
-    match op {
-        '+' => a + b,
-        '*' => a * b,
-        '|' => (a * 10u64.pow(b.ilog10() + 1)) + b,
-        _ => unreachable!(),
+/// A binary operator that equations are evaluated with, strictly left to right.
+trait Operator: Sync {
+    fn symbol(&self) -> &str;
+
+    /// Computes `a op b`, or `None` if the result is not a valid `u64`.
+    fn apply(&self, a: u64, b: u64) -> Option<u64>;
+
+    /// Finds the `a` for which `a op b == total`, or `None` if there is none. This is what
+    /// prunes the right-to-left search, so it should reject impossible cases early.
+    fn unapply(&self, total: u64, b: u64) -> Option<Inverse>;
+}
+
+/// The left operands `a` that satisfy `a op b == total`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Inverse {
+    Exactly(u64),
+    /// `b` absorbs `a`, as in `a * 0` or `a ^ 0`, so every `a` works.
+    Any,
+}
+
+struct Add;
+
+impl Operator for Add {
+    fn symbol(&self) -> &str {
+        "+"
+    }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_add(b)
+    }
+
+    fn unapply(&self, total: u64, b: u64) -> Option<Inverse> {
+        total.checked_sub(b).map(Inverse::Exactly)
     }
 }
 
-fn try_evaluate(total: u64, nums: &mut [u64], ops: &[char]) -> bool {
-    if nums.len() <= 1 {
-        return nums[0] == total;
+#[allow(dead_code)]
+struct Subtract;
+
+impl Operator for Subtract {
+    fn symbol(&self) -> &str {
+        "-"
     }
-    if nums[0] > total {
-        return false;
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_sub(b)
     }
 
-    for op in ops {
-        let old = nums[1];
-        nums[1] = apply(*op, nums[0], nums[1]);
-        if try_evaluate(total, &mut nums[1..], ops) {
-            return true;
-        }
-        nums[1] = old;
+    fn unapply(&self, total: u64, b: u64) -> Option<Inverse> {
+        total.checked_add(b).map(Inverse::Exactly)
     }
-    false
 }
 
-fn parse_equation(line: &str) -> (u64, Vec<u64>) {
-    let (total, rest) = line.split_once(": ").unwrap();
-    let nums = rest.split(' ').filter_map(|num| num.parse().ok()).collect();
+/// Multiplication. Multiplying by zero loses `a`, so a zero total then accepts any `a`.
+struct Multiply;
 
-    (total.parse().unwrap(), nums)
+impl Operator for Multiply {
+    fn symbol(&self) -> &str {
+        "*"
+    }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_mul(b)
+    }
+
+    fn unapply(&self, total: u64, b: u64) -> Option<Inverse> {
+        match b {
+            0 => (total == 0).then_some(Inverse::Any),
+            _ => total.is_multiple_of(b).then(|| Inverse::Exactly(total / b)),
+        }
+    }
 }
 
-pub fn part_one(input: &str) -> Option<u64> {
-    let opers = vec!['*', '+'];
+/// Exponentiation. A zeroth power loses `a`, so a total of one then accepts any `a`.
+#[allow(dead_code)]
+struct Exponent;
 
-    let calibration_result = input
-        .lines()
-        .par_bridge()
-        .filter_map(|line| {
-            let (total, mut nums) = parse_equation(line);
+impl Operator for Exponent {
+    fn symbol(&self) -> &str {
+        "^"
+    }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_pow(b.try_into().ok()?)
+    }
+
+    fn unapply(&self, total: u64, b: u64) -> Option<Inverse> {
+        let exp: u32 = match b.try_into().ok()? {
+            0 => return (total == 1).then_some(Inverse::Any),
+            1 => return Some(Inverse::Exactly(total)),
+            exp => exp,
+        };
 
-            if try_evaluate(total, &mut nums, &opers) {
-                Some(total)
-            } else {
-                None
+        // Integer root by binary search over the only candidates that cannot overflow
+        let (mut low, mut high) = (0u64, total.min(1 << (64 / exp)) + 1);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match mid.checked_pow(exp) {
+                Some(value) if value < total => low = mid + 1,
+                _ => high = mid,
             }
+        }
+
+        (low.checked_pow(exp) == Some(total)).then_some(Inverse::Exactly(low))
+    }
+}
+
+/// Appends the digits of `b` to `a`, written in the given base.
+struct Concat {
+    base: u64,
+}
+
+impl Concat {
+    /// Panics if `base < 2`, which has no positional digits; in a `const` this fails the build.
+    const fn new(base: u64) -> Self {
+        assert!(base >= 2, "concatenation needs a base of at least 2");
+        Self { base }
+    }
+
+    /// `base` raised to the number of digits in `n`.
+    fn shift(&self, n: u64) -> Option<u64> {
+        let digits = if n == 0 { 1 } else { n.ilog(self.base) + 1 };
+        self.base.checked_pow(digits)
+    }
+}
+
+impl Operator for Concat {
+    fn symbol(&self) -> &str {
+        "||"
+    }
+
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_mul(self.shift(b)?)?.checked_add(b)
+    }
+
+    fn unapply(&self, total: u64, b: u64) -> Option<Inverse> {
+        let shift = self.shift(b)?;
+        (total % shift == b).then(|| Inverse::Exactly(total / shift))
+    }
+}
+
+const PART_ONE_OPERATORS: &[&dyn Operator] = &[&Multiply, &Add];
+const PART_TWO_OPERATORS: &[&dyn Operator] = &[&Multiply, &Add, &Concat::new(10)];
+
+/// Searches for operators that make `nums` evaluate to `total`, working from the last number
+/// back to the first: each operator is undone against the running total, so impossible
+/// branches (indivisible totals, mismatched suffixes, ...) are cut off immediately.
+///
+/// Returns the operators in left-to-right order.
+fn solve<'a>(total: u64, nums: &[u64], ops: &[&'a dyn Operator]) -> Option<Vec<&'a dyn Operator>> {
+    let (&last, rest) = nums.split_last()?;
+
+    if rest.is_empty() {
+        return (last == total).then(Vec::new);
+    }
+
+    ops.iter().find_map(|&op| {
+        let mut solution = match op.unapply(total, last)? {
+            Inverse::Exactly(remaining) => solve(remaining, rest, ops)?,
+            Inverse::Any => evaluable(rest, ops)?,
+        };
+        solution.push(op);
+        Some(solution)
+    })
+}
+
+/// Finds any operators for which `nums` evaluates without overflowing, in left-to-right order.
+fn evaluable<'a>(nums: &[u64], ops: &[&'a dyn Operator]) -> Option<Vec<&'a dyn Operator>> {
+    fn search<'a>(
+        acc: u64,
+        nums: &[u64],
+        ops: &[&'a dyn Operator],
+    ) -> Option<Vec<&'a dyn Operator>> {
+        let Some((&next, rest)) = nums.split_first() else {
+            return Some(Vec::new());
+        };
+        ops.iter().find_map(|&op| {
+            let mut solution = search(op.apply(acc, next)?, rest, ops)?;
+            solution.push(op);
+            Some(solution)
         })
-        .sum();
+    }
 
-    Some(calibration_result)
+    let (&first, rest) = nums.split_first()?;
+    let mut solution = search(first, rest, ops)?;
+    solution.reverse();
+    Some(solution)
 }
 
-pub fn part_two(input: &str) -> Option<u64> {
-    let opers = vec!['*', '+', '|'];
+#[allow(dead_code)]
+fn format_equation(total: u64, nums: &[u64], ops: &[&dyn Operator]) -> String {
+    let mut equation = format!("{total} = {}", nums[0]);
+    for (op, num) in ops.iter().zip(&nums[1..]) {
+        equation.push_str(&format!(" {} {num}", op.symbol()));
+    }
+    equation
+}
+
+fn parse_equation(line: &str) -> (u64, Vec<u64>) {
+    let (total, rest) = line.split_once(": ").unwrap();
+    let nums = rest.split(' ').filter_map(|num| num.parse().ok()).collect();
 
-    let calibration_result = input
+    (total.parse().unwrap(), nums)
+}
+
+fn calibration_result(input: &str, ops: &[&dyn Operator]) -> u64 {
+    input
         .lines()
         .par_bridge()
         .filter_map(|line| {
-            let (total, mut nums) = parse_equation(line);
+            let (total, nums) = parse_equation(line);
 
-            if try_evaluate(total, &mut nums, &opers) {
-                Some(total)
-            } else {
-                None
-            }
+            let _solution = solve(total, &nums, ops)?;
+            //println!("{}", format_equation(total, &nums, &_solution));
+
+            Some(total)
         })
-        .sum();
+        .sum()
+}
 
-    Some(calibration_result)
+pub fn part_one(input: &str) -> Option<u64> {
+    Some(calibration_result(input, PART_ONE_OPERATORS))
+}
+
+pub fn part_two(input: &str) -> Option<u64> {
+    Some(calibration_result(input, PART_TWO_OPERATORS))
 }
 
 #[cfg(test)]
@@ -92,4 +245,114 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(11387));
     }
+
+    /// Evaluates left to right with the given operators.
+    fn evaluate(nums: &[u64], ops: &[&dyn Operator]) -> Option<u64> {
+        ops.iter()
+            .zip(&nums[1..])
+            .try_fold(nums[0], |acc, (op, &num)| op.apply(acc, num))
+    }
+
+    #[test]
+    fn test_solutions_evaluate_to_total() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+
+        for line in input.lines() {
+            let (total, nums) = parse_equation(line);
+            if let Some(ops) = solve(total, &nums, PART_TWO_OPERATORS) {
+                assert_eq!(evaluate(&nums, &ops), Some(total), "{line}");
+            }
+        }
+
+        let ops = solve(7290, &[6, 8, 6, 15], PART_TWO_OPERATORS).unwrap();
+        assert_eq!(
+            format_equation(7290, &[6, 8, 6, 15], &ops),
+            "7290 = 6 * 8 || 6 * 15"
+        );
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let ops: &[&dyn Operator] = &[&Add, &Subtract, &Exponent, &Concat::new(2)];
+
+        let solution = solve(5, &[10, 2, 3], ops).unwrap();
+        assert_eq!(format_equation(5, &[10, 2, 3], &solution), "5 = 10 - 2 - 3");
+
+        let solution = solve(512, &[2, 3, 3], ops).unwrap();
+        assert_eq!(
+            format_equation(512, &[2, 3, 3], &solution),
+            "512 = 2 ^ 3 ^ 3"
+        );
+
+        // 0b1 || 0b101 == 0b1101
+        let solution = solve(13, &[1, 5], ops).unwrap();
+        assert_eq!(format_equation(13, &[1, 5], &solution), "13 = 1 || 5");
+
+        assert!(solve(7, &[2, 2], ops).is_none());
+    }
+
+    #[test]
+    fn test_unapply_inverts_apply() {
+        let ops: &[&dyn Operator] = &[
+            &Add,
+            &Subtract,
+            &Multiply,
+            &Exponent,
+            &Concat::new(10),
+            &Concat::new(3),
+        ];
+
+        for op in ops {
+            for a in 0..40 {
+                for b in 0..6 {
+                    if let Some(total) = op.apply(a, b) {
+                        let inverse = op.unapply(total, b);
+                        assert!(
+                            matches!(inverse, Some(Inverse::Any))
+                                || inverse == Some(Inverse::Exactly(a)),
+                            "{a} {} {b}",
+                            op.symbol()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiply_by_zero() {
+        assert_eq!(Multiply.unapply(0, 0), Some(Inverse::Any));
+        assert_eq!(Multiply.unapply(3, 0), None);
+
+        let solution = solve(0, &[7, 4, 0], &[&Add, &Multiply]).unwrap();
+        assert_eq!(format_equation(0, &[7, 4, 0], &solution), "0 = 7 + 4 * 0");
+        assert!(solve(0, &[u64::MAX, 2, 0], &[&Multiply]).is_none());
+    }
+
+    #[test]
+    fn test_zeroth_power() {
+        assert_eq!(Exponent.unapply(1, 0), Some(Inverse::Any));
+        assert_eq!(Exponent.unapply(2, 0), None);
+
+        let solution = solve(1, &[7, 3, 0], &[&Exponent]).unwrap();
+        assert_eq!(format_equation(1, &[7, 3, 0], &solution), "1 = 7 ^ 3 ^ 0");
+    }
+
+    #[test]
+    fn test_first_power_of_large_total() {
+        assert_eq!(
+            Exponent.unapply(u64::MAX, 1),
+            Some(Inverse::Exactly(u64::MAX))
+        );
+        assert_eq!(
+            Exponent.unapply(u64::from(u32::MAX).pow(2), 2),
+            Some(Inverse::Exactly(u64::from(u32::MAX)))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "base of at least 2")]
+    fn test_concat_rejects_base_one() {
+        Concat::new(1);
+    }
 }
@@ -85,8 +85,8 @@ pub fn part_two(input: &str) -> Option<u32> {
     let antinodes = find_antinodes(&grid, min_bounds, max_bounds, true);
 
     let antennas = grid
-        .iter()
-        .flat_map(|(_, positions)| positions.clone())
+        .values()
+        .flat_map(|positions| positions.clone())
         .collect::<Vec<IVec2>>();
 
     // Combine the vectors
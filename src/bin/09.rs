@@ -1,5 +1,108 @@
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+
 advent_of_code::solution!(9);
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Span {
+    pos: usize,
+    len: usize,
+}
+
+/// The disk as a list of file spans indexed by file id; everything else is free space.
+struct Disk {
+    files: Vec<Span>,
+    size: usize,
+}
+
+impl Disk {
+    fn parse(input: &str) -> Self {
+        let mut files = Vec::new();
+        let mut pos = 0;
+
+        for (i, len) in input
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .map(|n| n as usize)
+            .enumerate()
+        {
+            if i % 2 == 0 {
+                files.push(Span { pos, len });
+            }
+            pos += len;
+        }
+
+        Self { files, size: pos }
+    }
+
+    /// Moves each whole file, highest id first, into the leftmost free run that fits it.
+    ///
+    /// Free runs are bucketed by length into min-heaps of their start position, so finding the
+    /// leftmost fitting run only looks at the top of each of the ten buckets.
+    fn compact_files(&mut self) {
+        let mut free_by_len: [BinaryHeap<Reverse<usize>>; 10] = Default::default();
+        for (file, next) in self.files.iter().tuple_windows() {
+            let end = file.pos + file.len;
+            if next.pos > end {
+                free_by_len[next.pos - end].push(Reverse(end));
+            }
+        }
+
+        for file in self.files.iter_mut().rev() {
+            let Some((len, pos)) = (file.len..free_by_len.len())
+                .filter_map(|len| free_by_len[len].peek().map(|&Reverse(pos)| (len, pos)))
+                .min_by_key(|&(_, pos)| pos)
+            else {
+                continue;
+            };
+
+            // Files only ever move left; the space they vacate is never reused because every
+            // remaining file lies further left
+            if pos >= file.pos {
+                continue;
+            }
+
+            free_by_len[len].pop();
+            if len > file.len {
+                free_by_len[len - file.len].push(Reverse(pos + file.len));
+            }
+            file.pos = pos;
+        }
+    }
+
+    fn checksum(&self) -> u64 {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(id, span)| {
+                // id * (pos + (pos + 1) + ... + (pos + len - 1))
+                let block_sum = span.len * span.pos + span.len * span.len.saturating_sub(1) / 2;
+                (id * block_sum) as u64
+            })
+            .sum()
+    }
+}
+
+impl fmt::Display for Disk {
+    /// Renders the disk in puzzle notation, e.g. `00...111...2...`. File ids are shown modulo 10.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut blocks = vec!['.'; self.size];
+        for (id, span) in self.files.iter().enumerate() {
+            let digit = char::from_digit((id % 10) as u32, 10).unwrap();
+            blocks[span.pos..span.pos + span.len].fill(digit);
+        }
+
+        write!(f, "{}", blocks.into_iter().collect::<String>())
+    }
+}
+
+#[allow(dead_code)]
+fn draw_disk(disk: &Disk) {
+    println!("{disk}");
+}
+
 pub fn part_one(input: &str) -> Option<u64> {
     let mut disk = Vec::new();
 
@@ -37,80 +140,14 @@ pub fn part_one(input: &str) -> Option<u64> {
     Some(checksum as u64)
 }
 
-pub fn part_two(input: &str) -> Option<i64> {
-    let mut program_id = 0;
-    let mut disk = Vec::new();
-
-    // Build initial disk state
-    for (i, c) in input.chars().enumerate() {
-        if !c.is_ascii_digit() {
-            continue;
-        }
-        let count = c.to_digit(10).unwrap() as usize;
-        if i % 2 == 0 {
-            disk.resize(disk.len() + count, program_id);
-            program_id += 1;
-        } else {
-            disk.resize(disk.len() + count, -1);
-        }
-    }
-
-    program_id -= 1;
-
-    while program_id > 0 {
-        let mut file_pos = None;
-        let mut file_len = 0;
-
-        // Single pass to find file start and length
-        for (i, &id) in disk.iter().enumerate() {
-            if id == program_id {
-                file_pos = file_pos.or(Some(i));
-                file_len += 1;
-            } else if file_len > 0 {
-                break;
-            }
-        }
-
-        let file_pos = file_pos.unwrap();
-
-        let mut free_space_offset = 0;
-        while free_space_offset < file_pos {
-            // Find the next contiguous free space
-            if disk[free_space_offset] == -1 {
-                let mut free_space_len = 0;
-                while free_space_offset + free_space_len < disk.len()
-                    && disk[free_space_offset + free_space_len] == -1
-                {
-                    free_space_len += 1;
-                }
-
-                // Check if the free space can fit the file
-                if free_space_len >= file_len {
-                    // Move file
-                    for i in 0..file_len {
-                        disk[free_space_offset + i] = program_id;
-                        disk[file_pos + i] = -1;
-                    }
-                    break;
-                }
-
-                free_space_offset += free_space_len;
-            }
-            free_space_offset += 1;
-        }
-
-        program_id -= 1;
-    }
+pub fn part_two(input: &str) -> Option<u64> {
+    let mut disk = Disk::parse(input);
 
-    // Calculate checksum
-    let mut checksum = 0;
-    for (i, &id) in disk.iter().enumerate() {
-        if id != -1 {
-            checksum += i as i64 * id as i64;
-        }
-    }
+    //draw_disk(&disk);
+    disk.compact_files();
+    //draw_disk(&disk);
 
-    Some(checksum)
+    Some(disk.checksum())
 }
 
 #[cfg(test)]
@@ -128,4 +165,31 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(2858));
     }
+
+    #[test]
+    fn test_draw_disk() {
+        let mut disk = Disk::parse(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(
+            disk.to_string(),
+            "00...111...2...333.44.5555.6666.777.888899"
+        );
+
+        disk.compact_files();
+        assert_eq!(
+            disk.to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+    }
+
+    #[test]
+    fn test_part_two_no_file_fits() {
+        // 0..111....22222: both gaps are too small for the files after them
+        assert_eq!(part_two("12345"), Some(132));
+    }
+
+    #[test]
+    fn test_part_two_no_free_space() {
+        // 012: every gap has length zero
+        assert_eq!(part_two("10101"), Some(5));
+    }
 }
@@ -1,89 +1,157 @@
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
 advent_of_code::solution!(11);
 
-#[derive(Debug)]
-struct Stones {
-    counts: HashMap<usize, usize>,
-    size: usize,
+/// A rule that may rewrite a stone when you blink. Rules are tried in order and the first one
+/// that applies wins; a stone no rule applies to stays as it is.
+trait Rule {
+    /// The stones that `n` turns into, or `None` if the rule does not apply.
+    fn apply(&self, n: u64) -> Result<Option<Vec<u64>>, Overflow>;
 }
 
-impl Stones {
-    fn new(stones: &[usize]) -> Self {
-        let counts = stones.iter().fold(HashMap::new(), |mut counts, &n| {
-            *counts.entry(n).or_insert(0) += 1;
-            counts
-        });
+/// A rule would engrave a number that does not fit in a `u64`.
+#[derive(Debug, PartialEq, Eq)]
+struct Overflow;
 
-        Self {
-            counts,
-            size: stones.len(),
-        }
+/// Engraves `to` on every stone engraved with `from`.
+struct Replace {
+    from: u64,
+    to: u64,
+}
+
+impl Rule for Replace {
+    fn apply(&self, n: u64) -> Result<Option<Vec<u64>>, Overflow> {
+        Ok((n == self.from).then(|| vec![self.to]))
     }
+}
 
-    fn blink(&mut self) {
-        let mut new_counts = HashMap::new();
-        for (&n, &count) in &self.counts {
-            match halve(n) {
-                Some((a, b)) => {
-                    *new_counts.entry(a).or_insert(0) += count;
-                    *new_counts.entry(b).or_insert(0) += count;
-                    self.size += count;
-                }
-                None if n == 0 => {
-                    *new_counts.entry(1).or_insert(0) += count;
-                }
-                None => {
-                    *new_counts.entry(n * 2024).or_insert(0) += count;
-                }
-            }
-        }
-        self.counts = new_counts;
+/// Splits a stone with an even number of digits into its left and right halves.
+struct SplitEvenDigits;
+
+impl Rule for SplitEvenDigits {
+    fn apply(&self, n: u64) -> Result<Option<Vec<u64>>, Overflow> {
+        Ok(halve(n).map(|(a, b)| vec![a, b]))
+    }
+}
+
+/// Multiplies the engraved number.
+struct Multiply {
+    factor: u64,
+}
+
+impl Rule for Multiply {
+    fn apply(&self, n: u64) -> Result<Option<Vec<u64>>, Overflow> {
+        let product = n.checked_mul(self.factor).ok_or(Overflow)?;
+        Ok(Some(vec![product]))
     }
 }
 
-fn halve(n: usize) -> Option<(usize, usize)> {
+const RULES: &[&dyn Rule] = &[
+    &Replace { from: 0, to: 1 },
+    &SplitEvenDigits,
+    &Multiply { factor: 2024 },
+];
+
+fn halve(n: u64) -> Option<(u64, u64)> {
     if n == 0 {
         return None;
     }
 
     let digits = n.ilog10() + 1;
-    if digits % 2 == 0 {
-        let div = 10usize.pow(digits / 2);
+    if digits.is_multiple_of(2) {
+        let div = 10u64.pow(digits / 2);
         Some((n / div, n % div))
     } else {
         None
     }
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let stones: Vec<usize> = input
-        .split_whitespace()
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect();
+/// What `n` turns into under the first rule that applies.
+fn rewrite(rules: &[&dyn Rule], n: u64) -> Result<Vec<u64>, Overflow> {
+    for rule in rules {
+        if let Some(successors) = rule.apply(n)? {
+            return Ok(successors);
+        }
+    }
+    Ok(vec![n])
+}
+
+/// How many stones carry each engraved number.
+type Histogram = HashMap<u64, u128>;
+
+/// The line of stones, tracked as a histogram per blink since the order never matters.
+///
+/// What each distinct number turns into is cached, so the rules run once per number no matter
+/// how many stones carry it or how many blinks it reappears in.
+struct Stones<'a> {
+    rules: &'a [&'a dyn Rule],
+    successors: HashMap<u64, Vec<u64>>,
+    history: Vec<Histogram>,
+}
 
-    let mut stones = Stones::new(&stones);
+impl<'a> Stones<'a> {
+    fn new(stones: &[u64], rules: &'a [&'a dyn Rule]) -> Self {
+        let mut initial = Histogram::new();
+        for &n in stones {
+            *initial.entry(n).or_insert(0) += 1;
+        }
 
-    for _ in 0..25 {
-        stones.blink();
+        Self {
+            rules,
+            successors: HashMap::new(),
+            history: vec![initial],
+        }
     }
 
-    Some(stones.size as u32)
-}
+    fn parse(input: &str, rules: &'a [&'a dyn Rule]) -> Self {
+        let stones: Vec<u64> = input
+            .split_whitespace()
+            .map(|s| s.parse().unwrap())
+            .collect();
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let stones: Vec<usize> = input
-        .split_whitespace()
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect();
+        Self::new(&stones, rules)
+    }
 
-    let mut stones = Stones::new(&stones);
+    /// The distinct numbers and how many stones carry them after `blinks` blinks, or `None` if a
+    /// count no longer fits in a `u128` or a number in a `u64`.
+    fn histogram(&mut self, blinks: usize) -> Option<&Histogram> {
+        while self.history.len() <= blinks {
+            let current = self.history.last().unwrap();
+            let mut next = Histogram::with_capacity(current.len());
+
+            for (&n, &count) in current {
+                let successors = match self.successors.entry(n) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(rewrite(self.rules, n).ok()?),
+                };
+
+                for &successor in successors.iter() {
+                    let total = next.entry(successor).or_insert(0);
+                    *total = total.checked_add(count)?;
+                }
+            }
+
+            self.history.push(next);
+        }
 
-    for _ in 0..75 {
-        stones.blink();
+        Some(&self.history[blinks])
     }
 
-    Some(stones.size)
+    /// Number of stones after `blinks` blinks, or `None` if it does not fit in a `u128`.
+    fn count(&mut self, blinks: usize) -> Option<u128> {
+        self.histogram(blinks)?
+            .values()
+            .try_fold(0u128, |acc, &count| acc.checked_add(count))
+    }
+}
+
+pub fn part_one(input: &str) -> Option<u128> {
+    Stones::parse(input, RULES).count(25)
+}
+
+pub fn part_two(input: &str) -> Option<u128> {
+    Stones::parse(input, RULES).count(75)
 }
 
 #[cfg(test)]
@@ -101,4 +169,83 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(65601038650482));
     }
+
+    fn histogram_of(stones: &[u64]) -> Histogram {
+        Stones::new(stones, RULES).history.remove(0)
+    }
+
+    #[test]
+    fn test_histogram_at_each_blink() {
+        let mut stones =
+            Stones::parse(&advent_of_code::template::read_file("examples", DAY), RULES);
+
+        // Later blinks first, to check earlier ones are kept rather than recomputed
+        assert_eq!(
+            stones.histogram(6),
+            Some(&histogram_of(&[
+                2097446912, 14168, 4048, 2, 0, 2, 4, 40, 48, 2024, 40, 48, 80, 96, 2, 8, 6, 7, 6,
+                0, 3, 2,
+            ]))
+        );
+        assert_eq!(stones.histogram(0), Some(&histogram_of(&[125, 17])));
+        assert_eq!(stones.histogram(1), Some(&histogram_of(&[253000, 1, 7])));
+        assert_eq!(
+            stones.histogram(3),
+            Some(&histogram_of(&[512072, 1, 20, 24, 28676032]))
+        );
+        assert_eq!(stones.count(6), Some(22));
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        // Ones stay put, even numbers split into two halves and the rest are tripled
+        struct Halve;
+        impl Rule for Halve {
+            fn apply(&self, n: u64) -> Result<Option<Vec<u64>>, Overflow> {
+                Ok(n.is_multiple_of(2).then(|| vec![n / 2, n / 2]))
+            }
+        }
+        let rules: &[&dyn Rule] = &[&Replace { from: 1, to: 1 }, &Halve, &Multiply { factor: 3 }];
+        let mut stones = Stones::new(&[1, 4, 5], rules);
+
+        assert_eq!(stones.histogram(1), Some(&histogram_of(&[1, 2, 2, 15])));
+        assert_eq!(
+            stones.histogram(2),
+            Some(&histogram_of(&[1, 1, 1, 1, 1, 45]))
+        );
+        assert_eq!(stones.count(3), Some(6));
+    }
+
+    #[test]
+    fn test_counts_beyond_u64() {
+        // Every stone doubles on each blink, so one stone becomes 2^blinks
+        struct Duplicate;
+        impl Rule for Duplicate {
+            fn apply(&self, n: u64) -> Result<Option<Vec<u64>>, Overflow> {
+                Ok(Some(vec![n, n]))
+            }
+        }
+        let mut stones = Stones::new(&[7], &[&Duplicate]);
+
+        assert_eq!(stones.count(100), Some(1 << 100));
+        assert_eq!(stones.count(127), Some(1 << 127));
+        assert_eq!(stones.histogram(128), None);
+        assert_eq!(stones.count(128), None);
+        assert_eq!(stones.histogram(127).unwrap()[&7], 1 << 127);
+    }
+
+    #[test]
+    fn test_multiply_overflow() {
+        let multiply = Multiply { factor: 2024 };
+        assert_eq!(
+            multiply.apply(u64::MAX / 2024),
+            Ok(Some(vec![u64::MAX / 2024 * 2024]))
+        );
+        assert_eq!(multiply.apply(u64::MAX / 2024 + 1), Err(Overflow));
+
+        // An odd number of digits, so it is multiplied rather than split, past `u64::MAX`
+        let mut stones = Stones::new(&[u64::MAX / 1000], RULES);
+        assert_eq!(stones.histogram(1), None);
+        assert_eq!(stones.count(1), None);
+    }
 }
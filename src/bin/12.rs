@@ -1,5 +1,4 @@
 use glam::IVec2;
-use std::collections::{HashMap, HashSet};
 
 advent_of_code::solution!(12);
 
@@ -8,192 +7,173 @@ const DOWN: IVec2 = IVec2::new(0, -1);
 const LEFT: IVec2 = IVec2::new(-1, 0);
 const RIGHT: IVec2 = IVec2::new(1, 0);
 
-fn parse_map(input: &str) -> HashMap<IVec2, char> {
-    input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.chars().enumerate().map(move |(x, c)| {
-                let pos = IVec2::new(x as i32, y as i32);
-                (pos, c)
-            })
-        })
-        .collect()
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Region {
+    plant: char,
+    area: u32,
+    perimeter: u32,
+    sides: u32,
 }
 
-fn find_regions(map: &HashMap<IVec2, char>) -> Vec<(char, Vec<IVec2>)> {
-    let mut regions = Vec::new();
-    let mut visited = HashSet::new(); // Track visited positions to avoid redundant checks
+impl Region {
+    fn price(&self) -> u32 {
+        self.area * self.perimeter
+    }
 
-    for (&pos, &c) in map {
-        if visited.contains(&pos) {
-            continue;
-        }
+    fn bulk_price(&self) -> u32 {
+        self.area * self.sides
+    }
+}
 
-        // Perform a breadth-first search to find all connected regions with the same character
-        let mut region = Vec::new();
-        let mut queue = vec![pos];
+/// The garden as a dense grid in which every plot is labelled with the region it belongs to.
+struct Garden {
+    width: i32,
+    height: i32,
+    labels: Vec<usize>,
+    regions: Vec<Region>,
+}
 
-        while let Some(curr_pos) = queue.pop() {
-            if !visited.insert(curr_pos) {
-                continue; // Skip if already visited
-            }
+impl Garden {
+    fn parse(input: &str) -> Self {
+        let plants: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+        let height = plants.len() as i32;
+        let width = plants.first().map_or(0, Vec::len) as i32;
+
+        let mut garden = Self {
+            width,
+            height,
+            labels: vec![usize::MAX; (width * height) as usize],
+            regions: Vec::new(),
+        };
+
+        // Label connected plots of the same plant with a depth-first flood fill
+        for y in 0..height {
+            for x in 0..width {
+                let pos = IVec2::new(x, y);
+                if garden.label(pos).is_some() {
+                    continue;
+                }
 
-            region.push(curr_pos);
-
-            for &dir in &[
-                IVec2::new(0, 1),
-                IVec2::new(0, -1),
-                IVec2::new(1, 0),
-                IVec2::new(-1, 0),
-            ] {
-                let neighbor = curr_pos + dir;
-                if let Some(&neighbor_c) = map.get(&neighbor) {
-                    if neighbor_c == c {
-                        queue.push(neighbor);
+                let label = garden.regions.len();
+                let plant = plants[y as usize][x as usize];
+                garden.regions.push(Region {
+                    plant,
+                    area: 0,
+                    perimeter: 0,
+                    sides: 0,
+                });
+
+                let mut stack = vec![pos];
+                garden.set_label(pos, label);
+                while let Some(curr_pos) = stack.pop() {
+                    for dir in [UP, DOWN, LEFT, RIGHT] {
+                        let neighbor = curr_pos + dir;
+                        if garden.in_bounds(neighbor)
+                            && garden.label(neighbor).is_none()
+                            && plants[neighbor.y as usize][neighbor.x as usize] == plant
+                        {
+                            garden.set_label(neighbor, label);
+                            stack.push(neighbor);
+                        }
                     }
                 }
             }
         }
 
-        regions.push((c, region));
+        garden.measure_regions();
+        garden
     }
 
-    regions
-}
+    fn in_bounds(&self, pos: IVec2) -> bool {
+        pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height
+    }
 
-fn price_region(region: &[IVec2]) -> u32 {
-    let area = region.len() as u32;
-
-    // loop through each area and count the sides that are not touching another region
-    let mut perimeter = 0;
-    for &pos in region {
-        for &dir in &[
-            IVec2::new(0, 1),
-            IVec2::new(0, -1),
-            IVec2::new(1, 0),
-            IVec2::new(-1, 0),
-        ] {
-            let new_pos = pos + dir;
-            if !region.contains(&new_pos) {
-                perimeter += 1;
-            }
+    fn label(&self, pos: IVec2) -> Option<usize> {
+        if !self.in_bounds(pos) {
+            return None;
         }
+        let label = self.labels[(pos.y * self.width + pos.x) as usize];
+        (label != usize::MAX).then_some(label)
     }
 
-    area * perimeter
-}
-
-fn count_sides(region: &[IVec2]) -> usize {
-    let mut sides: Vec<Vec<(IVec2, IVec2)>> = Vec::new();
-
-    for i in 0..region.len() {
-        for &dir in &[UP, DOWN, LEFT, RIGHT] {
-            let pos = region[i];
-
-            let check_open_position = pos + dir;
-
-            if region.contains(&check_open_position) {
-                continue;
-            }
+    fn set_label(&mut self, pos: IVec2, label: usize) {
+        self.labels[(pos.y * self.width + pos.x) as usize] = label;
+    }
 
-            if sides.iter().any(|side| side.contains(&(pos, dir))) {
-                continue;
+    /// Accumulates area, perimeter and side counts for every region in one pass over the grid.
+    ///
+    /// A region has as many sides as corners, and each corner is found locally: looking at a plot
+    /// and two orthogonal neighbours, it is a convex corner if neither neighbour is in the region,
+    /// and a concave one if both are but the diagonal between them is not.
+    fn measure_regions(&mut self) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = IVec2::new(x, y);
+                let label = self.label(pos);
+                let same = |offset: IVec2| self.label(pos + offset) == label;
+
+                let perimeter = [UP, DOWN, LEFT, RIGHT]
+                    .into_iter()
+                    .filter(|&dir| !same(dir))
+                    .count() as u32;
+
+                let corners = [(UP, RIGHT), (RIGHT, DOWN), (DOWN, LEFT), (LEFT, UP)]
+                    .into_iter()
+                    .filter(|&(a, b)| {
+                        let (side_a, side_b) = (same(a), same(b));
+                        (!side_a && !side_b) || (side_a && side_b && !same(a + b))
+                    })
+                    .count() as u32;
+
+                let region = &mut self.regions[label.unwrap()];
+                region.area += 1;
+                region.perimeter += perimeter;
+                region.sides += corners;
             }
+        }
+    }
 
-            // if dir is up or down check for all contiguous sides left and right of the position to see if they are a valid continuation of the side
-            let mut side = vec![(pos, dir)];
-
-            if dir == UP || dir == DOWN {
-                let mut left = pos + LEFT;
-                let mut right = pos + RIGHT;
-
-                while region.contains(&left) {
-                    let check_open_position = left + dir;
-
-                    if region.contains(&check_open_position) {
-                        break;
-                    }
-
-                    side.push((left, dir));
-
-                    left += LEFT;
-                }
-
-                while region.contains(&right) {
-                    let check_open_position = right + dir;
-
-                    if region.contains(&check_open_position) {
-                        break;
-                    }
-
-                    side.push((right, dir));
-
-                    right += RIGHT;
-                }
-            } else if dir == LEFT || dir == RIGHT {
-                let mut up = pos + UP;
-                let mut down = pos + DOWN;
-
-                while region.contains(&up) {
-                    let check_open_position = up + dir;
-
-                    if region.contains(&check_open_position) {
-                        break;
-                    }
-
-                    side.push((up, dir));
-
-                    up += UP;
-                }
-
-                while region.contains(&down) {
-                    let check_open_position = down + dir;
-
-                    if region.contains(&check_open_position) {
-                        break;
-                    }
-
-                    side.push((down, dir));
-
-                    down += DOWN;
-                }
-            }
+    /// Renders the region label of every plot, followed by the measurements of each region.
+    fn dump(&self) -> String {
+        let cell_width = self.regions.len().saturating_sub(1).to_string().len();
+        let mut out = String::new();
+
+        for y in 0..self.height {
+            let row: Vec<String> = (0..self.width)
+                .map(|x| format!("{:>cell_width$}", self.label(IVec2::new(x, y)).unwrap()))
+                .collect();
+            out.push_str(&row.join(" "));
+            out.push('\n');
+        }
 
-            sides.push(side);
+        for (label, region) in self.regions.iter().enumerate() {
+            out.push_str(&format!(
+                "{label} {}: area {}, perimeter {}, sides {}\n",
+                region.plant, region.area, region.perimeter, region.sides
+            ));
         }
-    }
 
-    sides.len()
+        out
+    }
 }
 
-fn bulk_price_region(_name: &char, region: &[IVec2]) -> u32 {
-    let area = region.len() as u32;
-    let sides = count_sides(region);
-
-    area * sides as u32
+#[allow(dead_code)]
+fn draw_regions(garden: &Garden) {
+    print!("{}", garden.dump());
 }
 
 pub fn part_one(input: &str) -> Option<u32> {
-    let map = parse_map(input);
+    let garden = Garden::parse(input);
 
-    let pricing = find_regions(&map)
-        .iter()
-        .map(|(_, region)| price_region(region))
-        .sum();
+    //draw_regions(&garden);
 
-    Some(pricing)
+    Some(garden.regions.iter().map(Region::price).sum())
 }
 
 pub fn part_two(input: &str) -> Option<u32> {
-    let map = parse_map(input);
+    let garden = Garden::parse(input);
 
-    let pricing = find_regions(&map)
-        .iter()
-        .map(|(name, region)| bulk_price_region(name, region))
-        .sum();
-
-    Some(pricing)
+    Some(garden.regions.iter().map(Region::bulk_price).sum())
 }
 
 #[cfg(test)]
@@ -241,4 +221,44 @@ mod tests {
         ));
         assert_eq!(result, Some(368));
     }
+
+    #[test]
+    fn test_dump_regions() {
+        let garden = Garden::parse(&advent_of_code::template::read_file("examples", DAY));
+
+        assert_eq!(
+            garden.dump(),
+            "\
+0 0 0 0
+1 1 2 3
+1 1 2 2
+4 4 4 2
+0 A: area 4, perimeter 10, sides 4
+1 B: area 4, perimeter 8, sides 4
+2 C: area 4, perimeter 10, sides 8
+3 D: area 1, perimeter 4, sides 4
+4 E: area 3, perimeter 8, sides 4
+"
+        );
+    }
+
+    #[test]
+    fn test_enclosed_regions() {
+        // The O region surrounds four single-plot X regions, each adding four inner sides
+        let garden = Garden::parse(&advent_of_code::template::read_file_part(
+            "examples", DAY, 2,
+        ));
+
+        assert_eq!(garden.regions.len(), 5);
+        assert_eq!(
+            garden.regions[0],
+            Region {
+                plant: 'O',
+                area: 21,
+                perimeter: 36,
+                sides: 20,
+            }
+        );
+        assert!(garden.dump().starts_with("0 0 0 0 0\n0 1 0 2 0\n"));
+    }
 }
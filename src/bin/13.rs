@@ -1,13 +1,18 @@
+use advent_of_code::linear::{min_cost_nonnegative, solve_2x2, Solution2x2};
 use glam::I64Vec2;
 use once_cell::sync::Lazy;
 use rayon::prelude::*;
 use regex::Regex;
 
-advent_of_code::solution!(13);
+advent_of_code::solution!(13, parallel);
 
 static BUTTON_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"X\+(\d+), Y\+(\d+)").unwrap());
 static PRIZE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"X\=(\d+), Y\=(\d+)").unwrap());
 
+/// Tokens it costs to press each button.
+const COST_A: i64 = 3;
+const COST_B: i64 = 1;
+
 #[derive(Debug)]
 struct Game {
     button_a: I64Vec2,
@@ -24,34 +29,40 @@ impl Game {
         }
     }
 
-    fn solve(&self, offset: i64) -> Option<(i64, i64)> {
-        let pxo = self.prize.x + offset;
-        let pyo = self.prize.y + offset;
-
-        let denominator = self.button_b.y * self.button_a.x - self.button_b.x * self.button_a.y;
-        if denominator == 0 {
-            return None;
+    /// The cheapest number of presses of each button that reaches the prize moved by `offset`.
+    ///
+    /// If both buttons move the claw along the same line as the prize there are many ways to
+    /// reach it, and the one costing the fewest tokens is picked.
+    fn cheapest_win(&self, offset: i64) -> Option<(i64, i64)> {
+        let prize = self.prize + I64Vec2::splat(offset);
+
+        match solve_2x2(
+            self.button_a.to_array(),
+            self.button_b.to_array(),
+            prize.to_array(),
+        ) {
+            Solution2x2::Unique(m, n) => {
+                let (m, n) = (m.to_integer()?, n.to_integer()?);
+                if m < 0 || n < 0 {
+                    return None;
+                }
+                Some((i64::try_from(m).ok()?, i64::try_from(n).ok()?))
+            }
+            Solution2x2::Line { a, b, c } => {
+                if a < 0 || b < 0 {
+                    return None;
+                }
+                min_cost_nonnegative(a, b, c, COST_A, COST_B)
+            }
+            Solution2x2::None => None,
         }
+    }
 
-        let n = (pyo * self.button_a.x - pxo * self.button_a.y) / denominator;
-
-        let m = if self.button_a.x != 0 {
-            (pxo - n * self.button_b.x) / self.button_a.x
-        } else if self.button_a.y != 0 {
-            (pyo - n * self.button_b.y) / self.button_a.y
-        } else {
-            return None;
-        };
-
-        if m >= 0
-            && n >= 0
-            && m * self.button_a.x + n * self.button_b.x == pxo
-            && m * self.button_a.y + n * self.button_b.y == pyo
-        {
-            Some((m, n))
-        } else {
-            None
-        }
+    fn tokens(&self, offset: i64) -> u128 {
+        self.cheapest_win(offset).map_or(0, |(m, n)| {
+            u128::from(m.unsigned_abs()) * COST_A as u128
+                + u128::from(n.unsigned_abs()) * COST_B as u128
+        })
     }
 }
 
@@ -75,8 +86,8 @@ fn parse_prize(line: &str) -> Option<I64Vec2> {
     }
 }
 
-pub fn part_one(input: &str) -> Option<u64> {
-    let games: Vec<Game> = input
+fn parse_games(input: &str) -> Vec<Game> {
+    input
         .split("\n\n")
         .map(|game| {
             let mut lines = game.lines();
@@ -86,56 +97,112 @@ pub fn part_one(input: &str) -> Option<u64> {
 
             Game::new(button_a.unwrap(), button_b.unwrap(), prize.unwrap())
         })
-        .collect();
-
-    let cheapest_wins = games
-        .par_iter()
-        .map(|game| {
-            if let Some((m, n)) = game.solve(0) {
-                m.unsigned_abs() * 3 + n.unsigned_abs()
-            } else {
-                0
-            }
-        })
-        .sum();
-
-    Some(cheapest_wins)
+        .collect()
 }
 
-pub fn part_two(input: &str) -> Option<u64> {
-    let games: Vec<Game> = input
-        .split("\n\n")
-        .map(|game| {
-            let mut lines = game.lines();
-            let button_a = parse_button(lines.next().unwrap());
-            let button_b = parse_button(lines.next().unwrap());
-            let prize = parse_prize(lines.next().unwrap());
+pub fn part_one(input: &str) -> Option<u128> {
+    let games = parse_games(input);
 
-            Game::new(button_a.unwrap(), button_b.unwrap(), prize.unwrap())
-        })
-        .collect();
+    Some(games.par_iter().map(|game| game.tokens(0)).sum())
+}
 
-    let cheapest_wins = games
-        .par_iter()
-        .map(|game| {
-            if let Some((m, n)) = game.solve(10_000_000_000_000) {
-                m.unsigned_abs() * 3 + n.unsigned_abs()
-            } else {
-                0
-            }
-        })
-        .sum();
+pub fn part_two(input: &str) -> Option<u128> {
+    let games = parse_games(input);
 
-    Some(cheapest_wins)
+    Some(
+        games
+            .par_iter()
+            .map(|game| game.tokens(10_000_000_000_000))
+            .sum(),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code::random::Lcg;
 
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(480));
     }
+
+    #[test]
+    fn test_part_two() {
+        let result = part_two(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(result, Some(875318608908));
+    }
+
+    /// Cheapest win found by trying every press count up to `limit`.
+    fn brute_force(game: &Game, limit: i64) -> Option<(i64, i64)> {
+        (0..=limit)
+            .flat_map(|m| (0..=limit).map(move |n| (m, n)))
+            .filter(|&(m, n)| game.button_a * m + game.button_b * n == game.prize)
+            .min_by_key(|&(m, n)| m * COST_A + n * COST_B)
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut rng = Lcg::new(0x13);
+        let mut random = |n: u64| rng.below(n) as i64;
+
+        for i in 0..3000 {
+            let button_a = I64Vec2::new(random(6), random(6));
+            // Every third game has collinear buttons
+            let button_b = if i % 3 == 0 {
+                button_a * (random(4) + 1) / (random(2) + 1)
+            } else {
+                I64Vec2::new(random(6), random(6))
+            };
+            let prize = if i % 2 == 0 {
+                button_a * random(8) + button_b * random(8)
+            } else {
+                I64Vec2::new(random(40), random(40))
+            };
+            let game = Game::new(button_a, button_b, prize);
+
+            let expected = brute_force(&game, 40).map(|(m, n)| m * COST_A + n * COST_B);
+            let result = game.cheapest_win(0);
+            if let Some((m, n)) = result {
+                assert_eq!(
+                    game.button_a * m + game.button_b * n,
+                    game.prize,
+                    "{game:?}"
+                );
+            }
+            assert_eq!(
+                result.map(|(m, n)| m * COST_A + n * COST_B),
+                expected,
+                "{game:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_collinear_buttons() {
+        // B moves three times as far as A for a third of the price, so it is always preferred
+        let game = Game::new(I64Vec2::new(2, 1), I64Vec2::new(6, 3), I64Vec2::new(22, 11));
+        assert_eq!(game.cheapest_win(0), Some((2, 3)));
+
+        // Here A is the better deal and B is pressed only to fix the remainder
+        let game = Game::new(I64Vec2::new(5, 5), I64Vec2::new(1, 1), I64Vec2::new(23, 23));
+        assert_eq!(game.cheapest_win(0), Some((4, 3)));
+
+        let game = Game::new(I64Vec2::new(2, 1), I64Vec2::new(4, 2), I64Vec2::new(7, 7));
+        assert_eq!(game.cheapest_win(0), None);
+    }
+
+    #[test]
+    fn test_large_buttons() {
+        // Cramer's rule in i64 overflows here: the offset prize times a button is around 10^22
+        let button_a = I64Vec2::new(1_000_000_007, 999_999_937);
+        let button_b = I64Vec2::new(999_999_893, 1_000_000_009);
+        let offset = 10_000_000_000_000;
+        let prize = button_a * 20_000 + button_b * 30_000 - I64Vec2::splat(offset);
+
+        let game = Game::new(button_a, button_b, prize);
+        assert_eq!(game.cheapest_win(offset), Some((20_000, 30_000)));
+        assert_eq!(game.tokens(offset), 90_000);
+    }
 }
@@ -20,6 +20,10 @@ impl Trajectory {
         self.p.x = self.p.x.rem_euclid(extent.x);
         self.p.y = self.p.y.rem_euclid(extent.y);
     }
+
+    fn advanced(&self, seconds: i32, extent: &IVec2) -> Self {
+        Self::new((self.p + self.v * seconds).rem_euclid(*extent), self.v)
+    }
 }
 
 static TRAJECTORY_REGEX: Lazy<Regex> =
@@ -52,44 +56,80 @@ fn draw_grid(trajectories: &[Trajectory], extent: &IVec2) {
     println!();
 }
 
-fn max_contiguous_in_rows(trajectories: &[Trajectory], max: u32) -> bool {
-    // Sort the points by (y, x) to process rows and contiguous x-values in order
-    let mut sorted_points: Vec<IVec2> = trajectories.iter().map(|t| t.p).collect();
-    sorted_points.sort_unstable_by_key(|p| (p.y, p.x));
-
-    // Variables to track current row and contiguous x-values
-    let mut current_y = None;
-    let mut prev_x = None;
-    let mut current_contiguous = 0;
-
-    for point in sorted_points {
-        match current_y {
-            Some(y) if y == point.y => {
-                // Same row, check if x is contiguous
-                if let Some(prev_x) = prev_x {
-                    if point.x == prev_x + 1 {
-                        current_contiguous += 1;
-                        if current_contiguous >= max {
-                            return true;
-                        }
-                    } else {
-                        current_contiguous = 1;
-                    }
-                } else {
-                    current_contiguous = 1;
-                }
-                prev_x = Some(point.x);
-            }
-            _ => {
-                // New row, reset counters
-                current_y = Some(point.y);
-                prev_x = Some(point.x);
-                current_contiguous = 1;
-            }
+#[allow(dead_code)]
+fn draw_grid_at(trajectories: &[Trajectory], seconds: u32, extent: &IVec2) {
+    let advanced: Vec<Trajectory> = trajectories
+        .iter()
+        .map(|t| t.advanced(seconds as i32, extent))
+        .collect();
+    draw_grid(&advanced, extent);
+}
+
+/// Fraction of an even spread's variance below which robots count as clustered along an axis.
+const CLUSTER_RATIO: f64 = 0.7;
+
+/// Finds the time in `0..period` at which robots are most tightly clustered along one axis,
+/// given as `(position, velocity)` pairs, if that is markedly tighter than an even spread.
+fn clustered_time(axis: &[(i32, i32)], period: i32) -> Option<i32> {
+    let n = axis.len() as f64;
+
+    let (time, min_variance) = (0..period)
+        .map(|t| {
+            let coords = axis
+                .iter()
+                .map(|&(p, v)| (p + v * t).rem_euclid(period) as f64);
+            let mean = coords.clone().sum::<f64>() / n;
+            let variance = coords.map(|c| (c - mean).powi(2)).sum::<f64>() / n;
+            (t, variance)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    // Variance of a discrete uniform distribution over 0..period
+    let uniform_variance = f64::from(period * period - 1) / 12.0;
+
+    (min_variance < uniform_variance * CLUSTER_RATIO).then_some(time)
+}
+
+/// Solves `t ≡ a (mod m)` and `t ≡ b (mod n)`, returning the smallest non-negative `t`.
+fn chinese_remainder(a: i64, m: i64, b: i64, n: i64) -> Option<i64> {
+    fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+        if b == 0 {
+            (a, 1, 0)
+        } else {
+            let (g, x, y) = extended_gcd(b, a % b);
+            (g, y, x - (a / b) * y)
         }
     }
 
-    false
+    let (g, x, _) = extended_gcd(m, n);
+    if (b - a) % g != 0 {
+        return None;
+    }
+
+    let lcm = m / g * n;
+    let k = ((b - a) / g * x).rem_euclid(n / g);
+
+    Some((a + m * k).rem_euclid(lcm))
+}
+
+/// Finds the first second at which the robots form a picture, by combining the times at which
+/// they cluster horizontally and vertically. Since positions repeat every `extent.x * extent.y`
+/// seconds, that period bounds the search; `None` means no picture ever forms.
+fn find_tree(trajectories: &[Trajectory], extent: &IVec2) -> Option<u32> {
+    let xs: Vec<(i32, i32)> = trajectories.iter().map(|t| (t.p.x, t.v.x)).collect();
+    let ys: Vec<(i32, i32)> = trajectories.iter().map(|t| (t.p.y, t.v.y)).collect();
+
+    let time_x = clustered_time(&xs, extent.x)?;
+    let time_y = clustered_time(&ys, extent.y)?;
+
+    let seconds = chinese_remainder(
+        time_x.into(),
+        extent.x.into(),
+        time_y.into(),
+        extent.y.into(),
+    )?;
+
+    Some(seconds as u32)
 }
 
 pub fn part_one(input: &str) -> Option<u32> {
@@ -131,7 +171,7 @@ pub fn part_one(input: &str) -> Option<u32> {
 }
 
 pub fn part_two(input: &str) -> Option<u32> {
-    let mut trajectories: Vec<Trajectory> = input.lines().filter_map(parse_trajectory).collect();
+    let trajectories: Vec<Trajectory> = input.lines().filter_map(parse_trajectory).collect();
 
     let extent = if cfg!(test) {
         IVec2::new(11, 7)
@@ -139,30 +179,88 @@ pub fn part_two(input: &str) -> Option<u32> {
         IVec2::new(101, 103)
     };
 
-    let mut loops = 1;
-
-    loop {
-        for trajectory in trajectories.iter_mut() {
-            trajectory.step(&extent);
-        }
-        if max_contiguous_in_rows(&trajectories, 10) {
-            //draw_grid(&trajectories, &extent);
-            break;
-        }
+    let seconds = find_tree(&trajectories, &extent)?;
 
-        loops += 1;
-    }
+    //draw_grid_at(&trajectories, seconds, &extent);
 
-    Some(loops)
+    Some(seconds)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code::random::Lcg;
 
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(12));
     }
+
+    const EXTENT: IVec2 = IVec2::new(101, 103);
+
+    fn random_velocity(rng: &mut Lcg) -> IVec2 {
+        let mut next = || rng.below(201) as i32 - 100;
+        IVec2::new(next(), next())
+    }
+
+    fn random_position(rng: &mut Lcg) -> IVec2 {
+        random_velocity(rng).rem_euclid(EXTENT)
+    }
+
+    /// Robots that draw a framed triangle at `seconds`, plus some unrelated noise.
+    fn tree_at(seconds: i32) -> Vec<Trajectory> {
+        let mut rng = Lcg::new(0x14);
+        let mut picture = Vec::new();
+        for y in 0..33_i32 {
+            for x in 0..31_i32 {
+                let border = x == 0 || x == 30 || y == 0 || y == 32;
+                let tree = y > 2 && y < 30 && (x - 15).abs() <= (y - 3) / 2;
+                if border || tree {
+                    picture.push(IVec2::new(x + 40, y + 30));
+                }
+            }
+        }
+        let noise: Vec<IVec2> = (0..150).map(|_| random_position(&mut rng)).collect();
+
+        picture
+            .into_iter()
+            .chain(noise)
+            .map(|p| {
+                let v = random_velocity(&mut rng);
+                Trajectory::new(p, v).advanced(-seconds, &EXTENT)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_tree() {
+        for seconds in [0, 1, 7338, 10402] {
+            let trajectories = tree_at(seconds);
+            assert_eq!(find_tree(&trajectories, &EXTENT), Some(seconds as u32));
+
+            let tree: Vec<Trajectory> = trajectories
+                .iter()
+                .map(|t| t.advanced(seconds, &EXTENT))
+                .collect();
+            assert!(tree.iter().any(|t| t.p == IVec2::new(55, 33)));
+        }
+    }
+
+    #[test]
+    fn test_find_tree_without_pattern() {
+        let mut rng = Lcg::new(0x2024);
+        let trajectories: Vec<Trajectory> = (0..500)
+            .map(|_| Trajectory::new(random_position(&mut rng), random_velocity(&mut rng)))
+            .collect();
+
+        assert_eq!(find_tree(&trajectories, &EXTENT), None);
+    }
+
+    #[test]
+    fn test_chinese_remainder() {
+        assert_eq!(chinese_remainder(2, 3, 3, 5), Some(8));
+        assert_eq!(chinese_remainder(60, 101, 20, 103), Some(2080));
+        assert_eq!(chinese_remainder(1, 4, 2, 6), None);
+    }
 }
@@ -1,4 +1,6 @@
+use advent_of_code::animation::{self, Frame};
 use glam::IVec2;
+use std::path::Path;
 
 advent_of_code::solution!(15);
 
@@ -16,15 +18,38 @@ struct WarehouseObject {
     object_type: WarehouseObjectType,
 }
 
+/// All objects in the warehouse, plus a dense index from each cell to the object covering it.
+///
+/// Wide boxes stay single objects; each of their cells points at the same entry in `objects`.
 #[derive(Debug)]
 struct WarehouseMap {
     objects: Vec<WarehouseObject>,
+    extent: IVec2,
+    cells: Vec<Option<usize>>,
 }
 
 impl WarehouseMap {
-    fn new() -> Self {
+    fn new(extent: IVec2) -> Self {
         Self {
             objects: Vec::new(),
+            extent,
+            cells: vec![None; (extent.x * extent.y) as usize],
+        }
+    }
+
+    fn cell(&self, pos: IVec2) -> Option<usize> {
+        (pos.x >= 0 && pos.x < self.extent.x && pos.y >= 0 && pos.y < self.extent.y)
+            .then(|| (pos.y * self.extent.x + pos.x) as usize)
+    }
+
+    /// Points every cell covered by the object at `id` to `value`.
+    fn index_object(&mut self, id: usize, value: Option<usize>) {
+        let object = self.objects[id];
+        for dy in 0..object.size.y {
+            for dx in 0..object.size.x {
+                let cell = self.cell(object.pos + IVec2::new(dx, dy)).unwrap();
+                self.cells[cell] = value;
+            }
         }
     }
 
@@ -34,30 +59,21 @@ impl WarehouseMap {
             size,
             object_type,
         });
+        self.index_object(self.objects.len() - 1, Some(self.objects.len() - 1));
     }
 
     fn get(&self, pos: IVec2) -> Option<WarehouseObject> {
-        for object in &self.objects {
-            // check if the position is inside the object
-            if pos.x >= object.pos.x
-                && pos.x < object.pos.x + object.size.x
-                && pos.y >= object.pos.y
-                && pos.y < object.pos.y + object.size.y
-            {
-                return Some(*object);
-            }
-        }
-
-        None
+        let id = self.cells[self.cell(pos)?]?;
+        Some(self.objects[id])
     }
 
-    fn get_mut(&mut self, pos: IVec2) -> Option<&mut WarehouseObject> {
-        self.objects.iter_mut().find(|object| {
-            pos.x >= object.pos.x
-                && pos.x < object.pos.x + object.size.x
-                && pos.y >= object.pos.y
-                && pos.y < object.pos.y + object.size.y
-        })
+    /// Moves the object covering `pos` by `direction`, keeping the cell index up to date.
+    fn move_object(&mut self, pos: IVec2, direction: IVec2) {
+        let id = self.cells[self.cell(pos).unwrap()].unwrap();
+
+        self.index_object(id, None);
+        self.objects[id].pos += direction;
+        self.index_object(id, Some(id));
     }
 
     fn can_push_box(&self, pos: IVec2, direction: IVec2) -> bool {
@@ -119,21 +135,25 @@ impl WarehouseMap {
             }
         }
 
-        let obj = self.get_mut(pos).unwrap();
-        obj.pos += direction;
+        self.move_object(pos, direction);
 
         true
     }
 }
 
 fn parse(input: &str, width: i32) -> (WarehouseMap, Vec<IVec2>, IVec2) {
-    let mut map = WarehouseMap::new();
     let mut directions = Vec::new();
     let mut robot = IVec2::new(0, 0);
     let size = IVec2::new(width, 1);
 
     let (map_input, directions_input) = input.split_once("\n\n").unwrap();
 
+    let extent = IVec2::new(
+        map_input.lines().map(str::len).max().unwrap_or(0) as i32 * width,
+        map_input.lines().count() as i32,
+    );
+    let mut map = WarehouseMap::new(extent);
+
     for (y, line) in map_input.lines().enumerate() {
         for (x, c) in line.chars().enumerate() {
             let x = x as i32 * width;
@@ -170,44 +190,39 @@ fn parse(input: &str, width: i32) -> (WarehouseMap, Vec<IVec2>, IVec2) {
     (map, directions, robot)
 }
 
-#[allow(dead_code)]
-fn draw_warehouse_map(map: &WarehouseMap, robot: IVec2) {
+fn render_warehouse(objects: &[WarehouseObject], robot: IVec2) -> Vec<String> {
     let mut min = IVec2::new(i32::MAX, i32::MAX);
     let mut max = IVec2::new(i32::MIN, i32::MIN);
 
-    for object in &map.objects {
+    for object in objects {
         min = min.min(object.pos);
         max = max.max(object.pos + object.size);
     }
 
-    for y in min.y..max.y {
-        for x in min.x..max.x {
-            let pos = IVec2::new(x, y);
-
-            if let Some(obj) = map.get(pos) {
-                match obj.object_type {
-                    WarehouseObjectType::Wall => {
-                        print!("#");
-                    }
-                    WarehouseObjectType::Box => {
-                        if obj.size.x == 2 {
-                            if obj.pos.x == x {
-                                print!("[");
-                            } else {
-                                print!("]");
-                            }
-                        } else {
-                            print!("O");
-                        }
-                    }
-                }
-            } else if pos == robot {
-                print!("@");
-            } else {
-                print!(".");
-            }
+    let mut rows = vec![vec!['.'; (max.x - min.x) as usize]; (max.y - min.y) as usize];
+    let mut paint =
+        |pos: IVec2, c: char| rows[(pos.y - min.y) as usize][(pos.x - min.x) as usize] = c;
+
+    for object in objects {
+        for dx in 0..object.size.x {
+            let c = match object.object_type {
+                WarehouseObjectType::Wall => '#',
+                WarehouseObjectType::Box if object.size.x == 1 => 'O',
+                WarehouseObjectType::Box if dx == 0 => '[',
+                WarehouseObjectType::Box => ']',
+            };
+            paint(object.pos + IVec2::new(dx, 0), c);
         }
-        println!();
+    }
+    paint(robot, '@');
+
+    rows.into_iter().map(String::from_iter).collect()
+}
+
+#[allow(dead_code)]
+fn draw_warehouse_map(map: &WarehouseMap, robot: IVec2) {
+    for row in render_warehouse(&map.objects, robot) {
+        println!("{row}");
     }
 }
 
@@ -222,14 +237,14 @@ fn get_move_char_from_direction(direction: IVec2) -> char {
     }
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let (mut map, directions, mut robot) = parse(input, 1);
-
-    //println!("Initial state:");
-    //draw_warehouse_map(&map, robot);
-    //println!();
-
-    for direction in directions {
+/// Moves the robot through every direction, calling `on_move` after each one.
+fn simulate(
+    map: &mut WarehouseMap,
+    directions: &[IVec2],
+    mut robot: IVec2,
+    mut on_move: impl FnMut(&WarehouseMap, IVec2, IVec2),
+) -> IVec2 {
+    for &direction in directions {
         let npos = robot + direction;
 
         if let Some(obj) = map.get(npos) {
@@ -245,11 +260,13 @@ pub fn part_one(input: &str) -> Option<u32> {
             robot = npos;
         }
 
-        //println!("Move: {}", get_move_char_from_direction(direction));
-        //draw_warehouse_map(&map, robot);
-        //println!();
+        on_move(map, robot, direction);
     }
 
+    robot
+}
+
+fn gps_sum(map: &WarehouseMap) -> u32 {
     // get all boxes from the map and multitply their y by 100 and add to their x, then sum all of them
     let sum: i32 = map
         .objects
@@ -260,47 +277,164 @@ pub fn part_one(input: &str) -> Option<u32> {
         })
         .sum();
 
-    Some(sum as u32)
+    sum as u32
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let (mut map, directions, mut robot) = parse(input, 2);
+/// What a single move changed: where the robot ended up, and the objects that were pushed.
+#[derive(Debug, Clone)]
+struct Step {
+    direction: IVec2,
+    robot: IVec2,
+    /// Index into the objects of the replay, and the new position of that object.
+    pushed: Vec<(usize, IVec2)>,
+}
 
-    //println!("Initial state:");
-    //draw_warehouse_map(&map, robot);
-    //println!();
+/// A recorded simulation that can be rendered frame by frame, in either direction.
+/// Only the changes of each move are kept, so long inputs stay small.
+struct Replay {
+    objects: Vec<WarehouseObject>,
+    robot: IVec2,
+    steps: Vec<Step>,
+}
 
-    for direction in directions {
-        let npos = robot + direction;
-        if let Some(obj) = map.get(npos) {
-            match obj.object_type {
-                WarehouseObjectType::Wall => {}
-                WarehouseObjectType::Box => {
-                    if map.push_box(npos, direction) {
-                        robot = npos;
-                    }
-                }
-            }
-        } else {
-            robot = npos;
+impl Replay {
+    fn record(input: &str, width: i32) -> Self {
+        let (mut map, directions, robot) = parse(input, width);
+        let objects = map.objects.clone();
+
+        let mut positions: Vec<IVec2> = objects.iter().map(|o| o.pos).collect();
+        let mut steps = Vec::with_capacity(directions.len());
+        simulate(&mut map, &directions, robot, |map, robot, direction| {
+            let pushed = map
+                .objects
+                .iter()
+                .zip(&mut positions)
+                .enumerate()
+                .filter(|(_, (object, pos))| object.pos != **pos)
+                .map(|(i, (object, pos))| {
+                    *pos = object.pos;
+                    (i, object.pos)
+                })
+                .collect();
+
+            steps.push(Step {
+                direction,
+                robot,
+                pushed,
+            });
+        });
+
+        Self {
+            objects,
+            robot,
+            steps,
         }
+    }
 
-        //println!("Move: {}", get_move_char_from_direction(direction));
-        //draw_warehouse_map(&map, robot);
-        //println!();
+    fn len(&self) -> usize {
+        self.steps.len() + 1
     }
 
-    // get all boxes from the map and multitply their y by 100 and add to their x, then sum all of them
-    let sum: i32 = map
-        .objects
-        .iter()
-        .filter_map(|o| match o.object_type {
-            WarehouseObjectType::Box => Some(o.pos.y * 100 + o.pos.x),
-            _ => None,
-        })
-        .sum();
+    fn caption(&self, index: usize) -> String {
+        match index.checked_sub(1).map(|i| &self.steps[i]) {
+            None => "Initial state:".to_string(),
+            Some(step) => format!(
+                "Move {} ({index}/{}):",
+                get_move_char_from_direction(step.direction),
+                self.steps.len()
+            ),
+        }
+    }
+
+    fn apply(objects: &mut [WarehouseObject], step: &Step) {
+        for &(i, pos) in &step.pushed {
+            objects[i].pos = pos;
+        }
+    }
+
+    /// Renders the state after `index` moves, replaying them from the start.
+    fn frame(&self, index: usize) -> Frame {
+        let mut objects = self.objects.clone();
+        for step in &self.steps[..index] {
+            Self::apply(&mut objects, step);
+        }
+        let robot = index
+            .checked_sub(1)
+            .map_or(self.robot, |i| self.steps[i].robot);
+
+        Frame::new(self.caption(index), render_warehouse(&objects, robot))
+    }
+
+    /// Renders every state in order, one at a time.
+    fn frames(&self) -> impl Iterator<Item = Frame> + '_ {
+        let initial = Frame::new(self.caption(0), render_warehouse(&self.objects, self.robot));
+
+        let moves =
+            self.steps
+                .iter()
+                .enumerate()
+                .scan(self.objects.clone(), |objects, (i, step)| {
+                    Self::apply(objects, step);
+                    Some(Frame::new(
+                        self.caption(i + 1),
+                        render_warehouse(objects, step.robot),
+                    ))
+                });
+
+        std::iter::once(initial).chain(moves)
+    }
+}
+
+/// Records a simulation and steps through it on the terminal.
+#[allow(dead_code)]
+fn step_replay(input: &str, width: i32) {
+    let replay = Replay::record(input, width);
+    animation::step_through(replay.len(), |index| replay.frame(index)).unwrap();
+}
+
+/// Records a simulation and writes it to `dir` as an animated GIF and a sequence of PPM images.
+#[allow(dead_code)]
+fn export_replay(input: &str, width: i32, dir: &Path) {
+    let replay = Replay::record(input, width);
+
+    animation::write_gif(
+        replay.frames(),
+        4,
+        5,
+        &dir.join(format!("{DAY}-replay.gif")),
+    )
+    .unwrap();
+    animation::write_ppm_sequence(replay.frames(), 4, &dir.join(format!("{DAY}-replay"))).unwrap();
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    let (mut map, directions, robot) = parse(input, 1);
 
-    Some(sum as u32)
+    //step_replay(input, 1);
+    //export_replay(input, 1, Path::new("data"));
+
+    simulate(&mut map, &directions, robot, |_map, _robot, _direction| {
+        //println!("Move: {}", get_move_char_from_direction(_direction));
+        //draw_warehouse_map(_map, _robot);
+        //println!();
+    });
+
+    Some(gps_sum(&map))
+}
+
+pub fn part_two(input: &str) -> Option<u32> {
+    let (mut map, directions, robot) = parse(input, 2);
+
+    //step_replay(input, 2);
+    //export_replay(input, 2, Path::new("data"));
+
+    simulate(&mut map, &directions, robot, |_map, _robot, _direction| {
+        //println!("Move: {}", get_move_char_from_direction(_direction));
+        //draw_warehouse_map(_map, _robot);
+        //println!();
+    });
+
+    Some(gps_sum(&map))
 }
 
 #[cfg(test)]
@@ -332,4 +466,97 @@ mod tests {
         ));
         assert_eq!(result, Some(9021));
     }
+
+    /// The original lookup, scanning every object for one covering `pos`.
+    fn linear_get(map: &WarehouseMap, pos: IVec2) -> Option<WarehouseObject> {
+        map.objects.iter().copied().find(|object| {
+            pos.x >= object.pos.x
+                && pos.x < object.pos.x + object.size.x
+                && pos.y >= object.pos.y
+                && pos.y < object.pos.y + object.size.y
+        })
+    }
+
+    fn assert_index_matches_linear(map: &WarehouseMap) {
+        for y in -1..=map.extent.y {
+            for x in -1..=map.extent.x {
+                let pos = IVec2::new(x, y);
+                let (indexed, linear) = (map.get(pos), linear_get(map, pos));
+                assert_eq!(indexed.map(|o| o.pos), linear.map(|o| o.pos), "at {pos}");
+                assert_eq!(indexed.map(|o| o.size), linear.map(|o| o.size), "at {pos}");
+                assert_eq!(
+                    indexed.map(|o| o.object_type),
+                    linear.map(|o| o.object_type),
+                    "at {pos}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_matches_linear_lookup() {
+        for (part, width) in [(2, 1), (2, 2), (3, 2)] {
+            let input = advent_of_code::template::read_file_part("examples", DAY, part);
+            let (mut map, directions, robot) = parse(&input, width);
+
+            assert_index_matches_linear(&map);
+            simulate(&mut map, &directions, robot, |map, _robot, _direction| {
+                assert_index_matches_linear(map);
+            });
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let replay = Replay::record(&advent_of_code::template::read_file("examples", DAY), 1);
+        let frames: Vec<Frame> = replay.frames().collect();
+
+        assert_eq!(frames.len(), 16);
+        assert_eq!(replay.len(), 16);
+        assert_eq!(replay.frame(15), frames[15]);
+        assert_eq!(replay.frame(3), frames[3]);
+        assert_eq!(frames[0].caption, "Initial state:");
+        assert_eq!(frames[1].caption, "Move < (1/15):");
+        assert_eq!(
+            frames[15].rows,
+            vec![
+                "########", "#....OO#", "##.....#", "#.....O#", "#.#O@..#", "#...O..#", "#...O..#",
+                "########",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay_wide() {
+        let replay = Replay::record(
+            &advent_of_code::template::read_file_part("examples", DAY, 3),
+            2,
+        );
+        let frames: Vec<Frame> = replay.frames().collect();
+
+        assert_eq!(
+            frames[0].rows,
+            vec![
+                "##############",
+                "##......##..##",
+                "##..........##",
+                "##....[][]@.##",
+                "##....[]....##",
+                "##..........##",
+                "##############",
+            ]
+        );
+        assert_eq!(
+            frames.last().unwrap().rows,
+            vec![
+                "##############",
+                "##...[].##..##",
+                "##...@.[]...##",
+                "##....[]....##",
+                "##..........##",
+                "##..........##",
+                "##############",
+            ]
+        );
+    }
 }
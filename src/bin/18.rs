@@ -1,3 +1,4 @@
+use advent_of_code::disjoint_set::DisjointSet;
 use glam::IVec2;
 use pathfinding::prelude::bfs;
 use std::collections::HashSet;
@@ -84,6 +85,67 @@ pub fn part_one(input: &str) -> Option<u32> {
     Some(result.len() as u32 - 1)
 }
 
+/// Finds the index of the first byte that cuts the goal off from the start, in a single pass.
+///
+/// Starting from the fully corrupted memory space, bytes are removed again in reverse order while
+/// a disjoint set tracks which open cells are connected. The first byte whose removal joins the
+/// start and goal is the one that blocked the path.
+fn first_blocking_byte(corrupted: &[IVec2], start: &IVec2, goal: &IVec2) -> Option<usize> {
+    let width = goal.x - start.x + 1;
+    let index = |pos: IVec2| ((pos.y - start.y) * width + (pos.x - start.x)) as usize;
+    let cells = index(*goal) + 1;
+
+    // A byte only reopens its cell when it was the first to fall there
+    let mut first_fallen = vec![None; cells];
+    for (i, byte) in corrupted.iter().enumerate() {
+        first_fallen[index(*byte)].get_or_insert(i);
+    }
+
+    let mut open: Vec<bool> = first_fallen.iter().map(Option::is_none).collect();
+    let mut sets = DisjointSet::new(cells);
+
+    let open_cell = |pos: IVec2, open: &mut Vec<bool>, sets: &mut DisjointSet| {
+        open[index(pos)] = true;
+        for dir in &[
+            IVec2::new(0, 1),
+            IVec2::new(0, -1),
+            IVec2::new(1, 0),
+            IVec2::new(-1, 0),
+        ] {
+            let neighbour = pos + dir;
+            if in_bounds(&neighbour, start, goal) && open[index(neighbour)] {
+                sets.union(index(pos), index(neighbour));
+            }
+        }
+    };
+
+    for y in start.y..=goal.y {
+        for x in start.x..=goal.x {
+            let pos = IVec2::new(x, y);
+            if open[index(pos)] {
+                open_cell(pos, &mut open, &mut sets);
+            }
+        }
+    }
+
+    if sets.connected(index(*start), index(*goal)) {
+        return None;
+    }
+
+    for (i, &byte) in corrupted.iter().enumerate().rev() {
+        if first_fallen[index(byte)] != Some(i) {
+            continue;
+        }
+
+        open_cell(byte, &mut open, &mut sets);
+        if sets.connected(index(*start), index(*goal)) {
+            return Some(i);
+        }
+    }
+
+    None
+}
+
 pub fn part_two(input: &str) -> Option<String> {
     let corrupted = input
         .lines()
@@ -101,24 +163,16 @@ pub fn part_two(input: &str) -> Option<String> {
         IVec2::new(70, 70)
     };
 
-    let start_bytes = if cfg!(test) { 12 } else { 1024 };
+    let blocking = first_blocking_byte(&corrupted, &start, &goal)?;
 
-    let mut left = start_bytes;
-    let mut right = corrupted.len() - 1;
+    //let path = run_simulation(&corrupted, &start, &goal, blocking).unwrap();
+    //let fallen = corrupted[..=blocking].iter().copied().collect();
+    //draw_map(&fallen, &start, &goal, &path);
 
-    loop {
-        let midpoint = (left + right) / 2;
-        if left + 1 == right {
-            break;
-        }
-        if run_simulation(&corrupted, &start, &goal, midpoint).is_some() {
-            left = midpoint;
-        } else {
-            right = midpoint;
-        }
-    }
-
-    Some(format!("{},{}", corrupted[left].x, corrupted[left].y))
+    Some(format!(
+        "{},{}",
+        corrupted[blocking].x, corrupted[blocking].y
+    ))
 }
 
 #[cfg(test)]
@@ -136,4 +190,43 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some("6,1".to_string()));
     }
+
+    #[test]
+    fn test_first_blocking_byte_matches_bfs() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let corrupted: Vec<IVec2> = input
+            .lines()
+            .map(|line| {
+                let (x, y) = line.split_once(',').unwrap();
+                IVec2::new(x.parse().unwrap(), y.parse().unwrap())
+            })
+            .collect();
+        let (start, goal) = (IVec2::new(0, 0), IVec2::new(6, 6));
+
+        let blocking = first_blocking_byte(&corrupted, &start, &goal).unwrap();
+        assert!(run_simulation(&corrupted, &start, &goal, blocking).is_some());
+        assert!(run_simulation(&corrupted, &start, &goal, blocking + 1).is_none());
+    }
+
+    #[test]
+    fn test_first_blocking_byte_never_blocked() {
+        let corrupted = vec![IVec2::new(1, 0), IVec2::new(1, 1), IVec2::new(1, 0)];
+        let (start, goal) = (IVec2::new(0, 0), IVec2::new(2, 2));
+
+        assert_eq!(first_blocking_byte(&corrupted, &start, &goal), None);
+    }
+
+    #[test]
+    fn test_first_blocking_byte_with_repeats() {
+        // The wall is completed by the third byte; the repeat of the first changes nothing
+        let corrupted = vec![
+            IVec2::new(1, 0),
+            IVec2::new(1, 1),
+            IVec2::new(1, 2),
+            IVec2::new(1, 0),
+        ];
+        let (start, goal) = (IVec2::new(0, 0), IVec2::new(2, 2));
+
+        assert_eq!(first_blocking_byte(&corrupted, &start, &goal), Some(2));
+    }
 }
@@ -1,9 +1,129 @@
-use itertools::Itertools;
-use regex::Regex;
-use std::collections::{HashMap, HashSet};
-
 advent_of_code::solution!(19);
 
+/// Stripe colours in the order used to index trie children.
+const COLOURS: [u8; 5] = [b'w', b'u', b'b', b'r', b'g'];
+
+fn colour_index(c: u8) -> Option<usize> {
+    COLOURS.iter().position(|&colour| colour == c)
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: [Option<usize>; 5],
+    towel: Option<usize>,
+}
+
+/// Every towel pattern in a trie, so all towels that fit at a position of a design are found in
+/// a single walk.
+struct TowelTrie<'a> {
+    towels: Vec<&'a str>,
+    nodes: Vec<TrieNode>,
+}
+
+impl<'a> TowelTrie<'a> {
+    fn new(towels: Vec<&'a str>) -> Self {
+        let mut nodes = vec![TrieNode::default()];
+
+        for (i, towel) in towels.iter().enumerate() {
+            let mut node = 0;
+            for c in towel.bytes() {
+                let colour = colour_index(c).unwrap_or_else(|| panic!("Invalid stripe {c}"));
+                node = match nodes[node].children[colour] {
+                    Some(child) => child,
+                    None => {
+                        nodes.push(TrieNode::default());
+                        nodes[node].children[colour] = Some(nodes.len() - 1);
+                        nodes.len() - 1
+                    }
+                };
+            }
+            nodes[node].towel = Some(i);
+        }
+
+        Self { towels, nodes }
+    }
+
+    /// Towels that match `design` starting at `start`, as `(towel index, end position)`.
+    fn matches<'d>(
+        &'d self,
+        design: &'d [u8],
+        start: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + 'd {
+        let mut node = Some(0);
+        design[start..]
+            .iter()
+            .enumerate()
+            .map_while(move |(i, &c)| {
+                let next = self.nodes[node?].children[colour_index(c)?]?;
+                node = Some(next);
+                Some(self.nodes[next].towel.map(|towel| (towel, start + i + 1)))
+            })
+            .flatten()
+    }
+
+    /// Number of ways to arrange towels into the design from every position onwards, or `None`
+    /// where it does not fit in a `u64`. The first entry is the total, and the design is possible
+    /// exactly when it is not `Some(0)`.
+    fn arrangements_from(&self, design: &str) -> Vec<Option<u64>> {
+        let design = design.as_bytes();
+        let mut ways = vec![Some(0u64); design.len() + 1];
+        ways[design.len()] = Some(1);
+
+        for start in (0..design.len()).rev() {
+            ways[start] = self
+                .matches(design, start)
+                .try_fold(0u64, |acc, (_, end)| acc.checked_add(ways[end]?));
+        }
+
+        ways
+    }
+
+    /// Number of ways to arrange towels into the design, or `None` if it does not fit in a `u64`.
+    fn arrangements(&self, design: &str) -> Option<u64> {
+        self.arrangements_from(design)[0]
+    }
+
+    /// Lists every arrangement of towels that makes up the design. There can be astronomically
+    /// many, so this is meant for short designs only.
+    #[allow(dead_code)]
+    fn enumerate(&self, design: &str) -> Vec<Vec<&'a str>> {
+        let ways = self.arrangements_from(design);
+        let mut arrangements = Vec::new();
+        self.enumerate_from(
+            design.as_bytes(),
+            0,
+            &ways,
+            &mut Vec::new(),
+            &mut arrangements,
+        );
+        arrangements
+    }
+
+    fn enumerate_from(
+        &self,
+        design: &[u8],
+        start: usize,
+        ways: &[Option<u64>],
+        current: &mut Vec<&'a str>,
+        arrangements: &mut Vec<Vec<&'a str>>,
+    ) {
+        if start == design.len() {
+            arrangements.push(current.clone());
+            return;
+        }
+
+        // Only follow towels that leave a remainder which can still be completed
+        for (towel, end) in self
+            .matches(design, start)
+            .filter(|&(_, end)| ways[end] != Some(0))
+        {
+            current.push(self.towels[towel]);
+            self.enumerate_from(design, end, ways, current, arrangements);
+            current.pop();
+        }
+    }
+}
+
 fn parse(input: &str) -> (Vec<&str>, Vec<&str>) {
     let (towels_str, designs_str) = input.split_once("\n\n").unwrap();
 
@@ -15,63 +135,33 @@ fn parse(input: &str) -> (Vec<&str>, Vec<&str>) {
 
 pub fn part_one(input: &str) -> Option<u32> {
     let (towels, designs) = parse(input);
+    let trie = TowelTrie::new(towels);
 
-    let re = Regex::new(&format!(r#"^({})+$"#, towels.join("|"))).unwrap();
-
-    let count = designs.iter().filter(|design| re.is_match(design)).count();
+    let count = designs
+        .iter()
+        .filter(|design| trie.arrangements(design) != Some(0))
+        .count();
 
     Some(count as u32)
 }
 
-pub fn part_two(input: &str) -> Option<i128> {
-    let (patterns, test_cases) = input
-        .split("\n\n")
-        .collect_tuple()
-        .expect("should have an empty line");
-
-    let pattern_set: HashSet<&str> = patterns.split(", ").collect();
-    let mut memoization = HashMap::new();
-
-    let total_sum = test_cases
-        .lines()
-        .map(|line| {
-            fn dfs<'a>(
-                s: &'a str,
-                patterns: &HashSet<&'a str>,
-                memo: &mut HashMap<&'a str, i128>,
-            ) -> i128 {
-                if let Some(&cached_result) = memo.get(s) {
-                    return cached_result;
-                }
-
-                if s.is_empty() {
-                    return 1;
-                }
-
-                let result = (0..s.len())
-                    .filter_map(|i| {
-                        if patterns.contains(&s[..=i]) {
-                            Some(dfs(&s[i + 1..], patterns, memo))
-                        } else {
-                            None
-                        }
-                    })
-                    .sum();
-
-                memo.insert(s, result);
-                result
-            }
+pub fn part_two(input: &str) -> Option<u64> {
+    let (towels, designs) = parse(input);
+    let trie = TowelTrie::new(towels);
 
-            dfs(line, &pattern_set, &mut memoization)
-        })
-        .sum();
+    //println!("{:?}", trie.enumerate(designs[0]));
 
-    Some(total_sum)
+    designs.iter().try_fold(0u64, |acc, design| {
+        acc.checked_add(trie.arrangements(design)?)
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code::random::Lcg;
+    use regex::Regex;
+    use std::time::Instant;
 
     #[test]
     fn test_part_one() {
@@ -84,4 +174,87 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(16));
     }
+
+    #[test]
+    fn test_enumerate() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let (towels, _) = parse(&input);
+        let trie = TowelTrie::new(towels);
+
+        let mut arrangements = trie.enumerate("gbbr");
+        arrangements.sort();
+        assert_eq!(
+            arrangements,
+            vec![
+                vec!["g", "b", "b", "r"],
+                vec!["g", "b", "br"],
+                vec!["gb", "b", "r"],
+                vec!["gb", "br"],
+            ]
+        );
+        assert!(trie.enumerate("ubwu").is_empty());
+    }
+
+    #[test]
+    fn test_arrangements_overflow() {
+        // With towels of length one and two, a run of n stripes has Fibonacci(n + 1) arrangements
+        let trie = TowelTrie::new(vec!["r", "rr"]);
+        assert_eq!(
+            trie.arrangements(&"r".repeat(92)),
+            Some(12200160415121876738)
+        );
+        assert_eq!(trie.arrangements(&"r".repeat(93)), None);
+
+        let input = format!("r, rr\n\n{}\nrr\n", "r".repeat(100));
+        assert_eq!(part_one(&input), Some(2));
+        assert_eq!(part_two(&input), None);
+    }
+
+    /// The original approach: one alternation regex over every towel.
+    fn regex_part_one(input: &str) -> u32 {
+        let (towels, designs) = parse(input);
+        let re = Regex::new(&format!(r#"^({})+$"#, towels.join("|"))).unwrap();
+
+        designs.iter().filter(|design| re.is_match(design)).count() as u32
+    }
+
+    /// Random towels and designs in the shape of a puzzle input.
+    fn generate_input() -> String {
+        let mut rng = Lcg::new(0x19);
+        let mut random = |n: u64| rng.below(n) as usize;
+        let mut stripes =
+            |len: usize| -> String { (0..len).map(|_| COLOURS[random(5)] as char).collect() };
+
+        let towels: Vec<String> = (0..400).map(|i| stripes(2 + i % 7)).collect();
+        let designs: Vec<String> = (0..400).map(|i| stripes(40 + i % 20)).collect();
+
+        format!("{}\n\n{}\n", towels.join(", "), designs.join("\n"))
+    }
+
+    #[test]
+    fn test_trie_matches_regex() {
+        let input = generate_input();
+        assert_eq!(part_one(&input), Some(regex_part_one(&input)));
+    }
+
+    /// Times the trie against a single alternation regex over all 400 generated towels, including
+    /// building the regex. Matching is linear in the design either way, but the regex compiles
+    /// every towel into one large automaton that its lazy DFA has to explore state by state.
+    #[test]
+    #[ignore]
+    fn bench_part_one_against_regex() {
+        let input = generate_input();
+
+        let timer = Instant::now();
+        let expected = regex_part_one(&input);
+        let regex = timer.elapsed();
+
+        let timer = Instant::now();
+        let result = part_one(&input);
+        let trie = timer.elapsed();
+
+        println!("regex: {regex:.1?}, trie: {trie:.1?}");
+        assert_eq!(result, Some(expected));
+        assert!(trie < regex);
+    }
 }
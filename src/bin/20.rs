@@ -2,7 +2,7 @@ use glam::IVec2;
 use rayon::prelude::*;
 use std::collections::{HashMap, HashSet, VecDeque};
 
-advent_of_code::solution!(20);
+advent_of_code::solution!(20, parallel);
 
 fn parse(input: &str) -> (HashSet<IVec2>, IVec2, IVec2) {
     let mut grid = HashSet::new();
@@ -1,118 +1,218 @@
 use glam::IVec2;
-use memoize::memoize;
+use std::collections::{HashMap, VecDeque};
 
 advent_of_code::solution!(21);
-fn numpad(key: char) -> IVec2 {
-    match key {
-        '7' => IVec2::new(0, 0),
-        '8' => IVec2::new(0, 1),
-        '9' => IVec2::new(0, 2),
-        '4' => IVec2::new(1, 0),
-        '5' => IVec2::new(1, 1),
-        '6' => IVec2::new(1, 2),
-        '1' => IVec2::new(2, 0),
-        '2' => IVec2::new(2, 1),
-        '3' => IVec2::new(2, 2),
-        '0' => IVec2::new(3, 1),
-        'A' => IVec2::new(3, 2),
-        _ => panic!(),
+
+/// The door's numeric keypad. A space marks a gap the robot arm must never point at.
+const NUMPAD: &str = "\
+789
+456
+123
+ 0A";
+
+/// The directional keypad used by every robot and by you.
+const ARROWPAD: &str = "\
+\x20^A
+<v>";
+
+/// A keypad layout: the position of every key. The arm may only ever point at keys, so gaps
+/// are simply the cells without one.
+struct Keypad {
+    keys: HashMap<char, IVec2>,
+}
+
+impl Keypad {
+    fn parse(layout: &str) -> Self {
+        let mut keys = HashMap::new();
+
+        for (y, line) in layout.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c != ' ' {
+                    keys.insert(c, IVec2::new(x as i32, y as i32));
+                }
+            }
+        }
+
+        Self { keys }
+    }
+
+    fn is_key(&self, pos: IVec2) -> bool {
+        self.keys.values().any(|&key| key == pos)
+    }
+
+    fn position(&self, key: char) -> IVec2 {
+        *self
+            .keys
+            .get(&key)
+            .unwrap_or_else(|| panic!("Key {key:?} is not on this keypad"))
+    }
+
+    /// Every shortest sequence of direction presses (ending with `A`) that moves the arm from one
+    /// key to another over keys only, and presses it. Which of them is cheapest depends on the
+    /// keypads further up the chain, so all are returned. Empty if gaps cut the keys apart.
+    fn paths(&self, from: char, to: char) -> Vec<String> {
+        let (from, to) = (self.position(from), self.position(to));
+        let distances = self.distances(to);
+
+        let mut paths = Vec::new();
+        if distances.contains_key(&from) {
+            self.collect_paths(from, &distances, &mut String::new(), &mut paths);
+        }
+        paths
+    }
+
+    /// Fewest moves from every reachable key to `to`, by breadth-first search.
+    fn distances(&self, to: IVec2) -> HashMap<IVec2, usize> {
+        let mut distances = HashMap::from([(to, 0)]);
+        let mut queue = VecDeque::from([to]);
+
+        while let Some(pos) = queue.pop_front() {
+            for next in DIRECTIONS.map(|c| pos + step(c)) {
+                if self.is_key(next) && !distances.contains_key(&next) {
+                    distances.insert(next, distances[&pos] + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Extends `path` with every move that brings the arm one step closer, down to distance zero.
+    fn collect_paths(
+        &self,
+        arm: IVec2,
+        distances: &HashMap<IVec2, usize>,
+        path: &mut String,
+        paths: &mut Vec<String>,
+    ) {
+        let distance = distances[&arm];
+        if distance == 0 {
+            paths.push(format!("{path}A"));
+            return;
+        }
+
+        for c in DIRECTIONS {
+            if distances.get(&(arm + step(c))) == Some(&(distance - 1)) {
+                path.push(c);
+                self.collect_paths(arm + step(c), distances, path, paths);
+                path.pop();
+            }
+        }
     }
 }
 
-fn arrowpad(key: char) -> IVec2 {
-    match key {
-        '^' => IVec2::new(0, 1),
-        'A' => IVec2::new(0, 2),
-        '<' => IVec2::new(1, 0),
-        'v' => IVec2::new(1, 1),
-        '>' => IVec2::new(1, 2),
-        _ => panic!(),
+const DIRECTIONS: [char; 4] = ['^', 'v', '<', '>'];
+
+/// How pressing a direction key moves the arm. `A` presses the key under it instead.
+fn step(c: char) -> IVec2 {
+    match c {
+        '^' => IVec2::NEG_Y,
+        'v' => IVec2::Y,
+        '<' => IVec2::NEG_X,
+        '>' => IVec2::X,
+        _ => IVec2::ZERO,
     }
 }
 
-#[memoize]
-fn do_arrows(i: i32, j: i32, steps: usize, h_first: bool) -> usize {
-    let (ii, jj) = (i.unsigned_abs() as usize, j.unsigned_abs() as usize);
-    let mut chunk = vec![if i > 0 { '^' } else { 'v' }; ii];
-    chunk.extend(vec![if j > 0 { '<' } else { '>' }; jj]);
-
-    if h_first {
-        chunk.reverse();
-    }
-
-    chunk.push('A');
-
-    if steps == 0 {
-        chunk.len()
-    } else {
-        let mut loc = arrowpad('A');
-
-        chunk
-            .into_iter()
-            .map(|c| {
-                let n = arrowpad(c);
-                let p = loc;
-                loc = n;
-                let d = IVec2::new(p.x - n.x, p.y - n.y);
-                if d.x == 0 || d.y == 0 {
-                    // straight line, search only once, order is irrelevant
-                    do_arrows(d.x, d.y, steps - 1, false)
-                } else if n == IVec2::new(1, 0) && p.x == 0 {
-                    // must search down first
-                    do_arrows(d.x, d.y, steps - 1, false)
-                } else if p == IVec2::new(1, 0) && n.x == 0 {
-                    // must search horiz first
-                    do_arrows(d.x, d.y, steps - 1, true)
-                } else {
-                    // can search in either order
-                    std::cmp::min(
-                        do_arrows(d.x, d.y, steps - 1, false),
-                        do_arrows(d.x, d.y, steps - 1, true),
-                    )
-                }
-            })
+/// A chain of keypads where each robot's arm is driven by the keypad after it. The first keypad
+/// is the one the code is typed on, and the last is pressed directly by you.
+struct KeypadChain<'a> {
+    keypads: Vec<&'a Keypad>,
+    cache: HashMap<(usize, char, char), Option<usize>>,
+}
+
+impl<'a> KeypadChain<'a> {
+    fn new(door: &'a Keypad, arrowpad: &'a Keypad, robots: usize) -> Self {
+        let mut keypads = vec![door];
+        keypads.extend(std::iter::repeat_n(arrowpad, robots + 1));
+
+        Self {
+            keypads,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Fewest presses you need to move the arm over keypad `level` from `from` to `to` and press it,
+    /// or `None` if gaps cut the keys apart.
+    fn presses(&mut self, level: usize, from: char, to: char) -> Option<usize> {
+        if level == self.keypads.len() - 1 {
+            return Some(1);
+        }
+        if let Some(&presses) = self.cache.get(&(level, from, to)) {
+            return presses;
+        }
+
+        let presses = self.keypads[level]
+            .paths(from, to)
+            .iter()
+            .filter_map(|path| self.path_presses(level + 1, path))
+            .min();
+
+        self.cache.insert((level, from, to), presses);
+        presses
+    }
+
+    /// Fewest presses you need to type `sequence` on keypad `level`, starting from `A`.
+    fn path_presses(&mut self, level: usize, sequence: &str) -> Option<usize> {
+        std::iter::once('A')
+            .chain(sequence.chars())
+            .zip(sequence.chars())
+            .map(|(from, to)| self.presses(level, from, to))
             .sum()
     }
+
+    fn min_presses(&mut self, code: &str) -> Option<usize> {
+        self.path_presses(0, code)
+    }
+
+    /// The actual buttons you press to type `code`. Its length grows exponentially with the
+    /// number of robots, so this is only practical for short chains.
+    #[allow(dead_code)]
+    fn button_sequence(&mut self, code: &str) -> Option<String> {
+        self.expand(0, code)
+    }
+
+    fn expand(&mut self, level: usize, sequence: &str) -> Option<String> {
+        if level == self.keypads.len() - 1 {
+            return Some(sequence.to_string());
+        }
+
+        std::iter::once('A')
+            .chain(sequence.chars())
+            .zip(sequence.chars())
+            .map(|(from, to)| {
+                let (_, path) = self.keypads[level]
+                    .paths(from, to)
+                    .into_iter()
+                    .filter_map(|path| Some((self.path_presses(level + 1, &path)?, path)))
+                    .min_by_key(|(presses, _)| *presses)?;
+                self.expand(level + 1, &path)
+            })
+            .collect()
+    }
 }
 
-fn enter_sequence(sequence: &str, steps: usize) -> usize {
-    let mut loc = numpad('A');
-
-    sequence
-        .chars()
-        .map(|c| {
-            // either move horizontally or vertically first
-            // in some cases only one will be valid...
-            let n = numpad(c);
-            let p = loc;
-            let d = IVec2::new(loc.x - n.x, loc.y - n.y);
-            loc = n;
-            if p.x == 3 && n.y == 0 {
-                // must move up first
-                do_arrows(d.x, d.y, steps, false)
-            } else if p.y == 0 && n.x == 3 {
-                // must move right first
-                do_arrows(d.x, d.y, steps, true)
-            } else {
-                // move in either direction
-                std::cmp::min(
-                    do_arrows(d.x, d.y, steps, true),
-                    do_arrows(d.x, d.y, steps, false),
-                )
-            }
+/// `None` if a code cannot be typed without crossing a gap.
+fn complexity(input: &str, robots: usize) -> Option<usize> {
+    let (numpad, arrowpad) = (Keypad::parse(NUMPAD), Keypad::parse(ARROWPAD));
+    let mut chain = KeypadChain::new(&numpad, &arrowpad, robots);
+
+    input
+        .lines()
+        .map(|code| {
+            //println!("{code}: {}", chain.button_sequence(code));
+            Some(chain.min_presses(code)? * code[0..3].parse::<usize>().unwrap())
         })
-        .sum::<usize>()
-        * sequence[0..3].parse::<usize>().unwrap()
+        .sum()
 }
 
 pub fn part_one(input: &str) -> Option<usize> {
-    memoized_flush_do_arrows();
-    Some(input.lines().map(|l| enter_sequence(l, 2)).sum())
+    complexity(input, 2)
 }
 
 pub fn part_two(input: &str) -> Option<usize> {
-    memoized_flush_do_arrows();
-    Some(input.lines().map(|l| enter_sequence(l, 25)).sum())
+    complexity(input, 25)
 }
 
 #[cfg(test)]
@@ -124,4 +224,79 @@ mod tests {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(126384));
     }
+
+    /// Types `presses` on the last keypad of the chain and returns what comes out on the first,
+    /// panicking if any arm points at a gap.
+    fn replay(chain: &KeypadChain, presses: &str) -> String {
+        chain
+            .keypads
+            .iter()
+            .rev()
+            .skip(1)
+            .fold(presses.to_string(), |presses, keypad| {
+                let mut arm = keypad.position('A');
+                let mut output = String::new();
+                for c in presses.chars() {
+                    arm += step(c);
+                    assert!(keypad.is_key(arm), "arm crossed a gap");
+                    if c == 'A' {
+                        let key = keypad.keys.iter().find(|(_, &p)| p == arm).unwrap();
+                        output.push(*key.0);
+                    }
+                }
+                output
+            })
+    }
+
+    #[test]
+    fn test_button_sequence() {
+        let (numpad, arrowpad) = (Keypad::parse(NUMPAD), Keypad::parse(ARROWPAD));
+
+        for robots in 0..=3 {
+            let mut chain = KeypadChain::new(&numpad, &arrowpad, robots);
+            for code in advent_of_code::template::read_file("examples", DAY).lines() {
+                let sequence = chain.button_sequence(code).unwrap();
+                assert_eq!(Some(sequence.len()), chain.min_presses(code));
+                assert_eq!(replay(&chain, &sequence), code);
+            }
+        }
+
+        let mut chain = KeypadChain::new(&numpad, &arrowpad, 2);
+        assert_eq!(chain.button_sequence("029A").unwrap().len(), 68);
+    }
+
+    #[test]
+    fn test_custom_keypad() {
+        // A phone-style keypad with the gap in the top-left corner
+        let phone = Keypad::parse(" 12\n345\n678\n9A0");
+        let arrowpad = Keypad::parse(ARROWPAD);
+        let mut chain = KeypadChain::new(&phone, &arrowpad, 1);
+
+        let sequence = chain.button_sequence("190A").unwrap();
+        assert_eq!(replay(&chain, &sequence), "190A");
+        assert_eq!(Some(sequence.len()), chain.min_presses("190A"));
+    }
+
+    #[test]
+    fn test_interior_gap() {
+        // The gap sits between 7 and 9, so neither L-shaped route gets from 9 to 7
+        let keypad = Keypad::parse("7 9\n456\n12A");
+        assert_eq!(keypad.paths('4', '9'), [">>^A"]);
+        assert_eq!(keypad.paths('9', '7'), ["v<<^A"]);
+
+        let arrowpad = Keypad::parse(ARROWPAD);
+        let mut chain = KeypadChain::new(&keypad, &arrowpad, 1);
+        for code in ["49A", "79A"] {
+            let sequence = chain.button_sequence(code).unwrap();
+            assert_eq!(replay(&chain, &sequence), code);
+            assert_eq!(Some(sequence.len()), chain.min_presses(code));
+        }
+
+        // Gaps that cut a key off leave nothing to type it with
+        let keypad = Keypad::parse("7 9\n 5 \n12A");
+        assert!(keypad.paths('A', '9').is_empty());
+        let mut chain = KeypadChain::new(&keypad, &arrowpad, 1);
+        assert_eq!(chain.min_presses("9A"), None);
+        assert_eq!(chain.button_sequence("9A"), None);
+    }
 }
@@ -2,7 +2,7 @@ use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
 use rayon::prelude::*;
 
-advent_of_code::solution!(22);
+advent_of_code::solution!(22, parallel);
 
 fn mix(secret: u64, mixin: u64) -> u64 {
     mixin ^ secret
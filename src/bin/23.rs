@@ -1,79 +1,71 @@
+use advent_of_code::graph::Graph;
 use std::collections::HashMap;
 
 advent_of_code::solution!(23);
 
-fn parse(input: &str) -> HashMap<&str, Vec<&str>> {
-    let mut network: HashMap<&str, Vec<&str>> = HashMap::new();
+struct Network<'a> {
+    names: Vec<&'a str>,
+    graph: Graph,
+}
+
+fn parse(input: &str) -> Network<'_> {
+    let mut names: Vec<&str> = Vec::new();
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut edges = Vec::new();
 
     for line in input.lines() {
         let (pc1, pc2) = line.split_once('-').unwrap();
 
-        network.entry(pc1).or_default().push(pc2);
-        network.entry(pc2).or_default().push(pc1);
+        let mut id = |name| {
+            *ids.entry(name).or_insert_with(|| {
+                names.push(name);
+                names.len() - 1
+            })
+        };
+        edges.push((id(pc1), id(pc2)));
+    }
+
+    let mut graph = Graph::new(names.len());
+    for (a, b) in edges {
+        graph.add_edge(a, b);
     }
 
-    network
+    Network { names, graph }
 }
 
 pub fn part_one(input: &str) -> Option<u32> {
     let network = parse(input);
 
-    let mut interconnected: Vec<Vec<&str>> = Vec::new();
-
-    // Check all connections for interconnected triples
-    for (pc, connected) in &network {
-        for (i, &node1) in connected.iter().enumerate() {
-            for &node2 in &connected[i + 1..] {
-                if let Some(connections) = network.get(node1) {
-                    if connections.contains(&node2) {
-                        let mut cur = vec![*pc, node1, node2];
-                        cur.sort_unstable();
-
-                        // Check if any name starts with 't'
-                        if cur.iter().any(|&name| name.starts_with('t')) {
-                            interconnected.push(cur);
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // Remove duplicate groups
-    interconnected.sort();
-    interconnected.dedup();
+    let interconnected = network
+        .graph
+        .triangles()
+        .filter(|triangle| {
+            triangle
+                .iter()
+                .any(|&pc| network.names[pc].starts_with('t'))
+        })
+        .count();
 
-    Some(interconnected.len() as u32)
+    Some(interconnected as u32)
 }
 
 pub fn part_two(input: &str) -> Option<String> {
     let network = parse(input);
 
-    let mut parties: Vec<Vec<&str>> = Vec::new();
-
-    for (pc, connections) in network.iter() {
-        let mut party = vec![*pc];
-        // loop through all combinations of two connected computers and see if they are connected with each other
-        for &connection in connections {
-            if party
-                .iter()
-                .all(|&x| network.get(connection).unwrap().contains(&x))
-            {
-                party.push(connection);
-            }
-        }
-
-        party.sort_unstable();
+    let mut party: Vec<&str> = network
+        .graph
+        .maximum_clique()
+        .into_iter()
+        .map(|pc| network.names[pc])
+        .collect();
 
-        if !parties.contains(&party) {
-            parties.push(party);
-        }
+    if party.is_empty() {
+        return None;
     }
 
-    // Find the largest party
-    let largest_party = parties.into_iter().max_by_key(|party| party.len())?;
+    party.sort_unstable();
 
-    Some(largest_party.join(","))
+    Some(party.join(","))
 }
 
 #[cfg(test)]
@@ -91,4 +83,20 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some("co,de,ka,ta".to_string()));
     }
+
+    #[test]
+    fn test_part_two_greedy_decoys() {
+        // Every member of the largest party lists a decoy as its first connection, which
+        // defeats growing a party greedily from each computer in adjacency order.
+        let input = "ka-da\nkb-db\nkc-dc\nkd-dd\nda-db\ndc-dd\n\
+                     ka-kb\nka-kc\nka-kd\nkb-kc\nkb-kd\nkc-kd\n";
+        assert_eq!(part_two(input), Some("ka,kb,kc,kd".to_string()));
+    }
+
+    #[test]
+    fn test_part_two_disjoint_parties() {
+        // Two separate parties where the smaller one appears first in the input
+        let input = "aa-ab\nab-ac\naa-ac\nba-bb\nba-bc\nba-bd\nbb-bc\nbb-bd\nbc-bd\n";
+        assert_eq!(part_two(input), Some("ba,bb,bc,bd".to_string()));
+    }
 }
@@ -1,49 +1,223 @@
+use std::fmt;
+
 advent_of_code::solution!(25);
 
-#[derive(Debug)]
+const WIDTH: usize = 5;
+const HEIGHT: usize = 7;
+
+/// Tallest pin or key column that still leaves the other side room to fit.
+const MAX_PIN: u8 = (HEIGHT - 2) as u8;
+
+/// Number of distinct height profiles, with each column between zero and `MAX_PIN`.
+const PROFILES: usize = (MAX_PIN as usize + 1).pow(WIDTH as u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum SchematicType {
     Key,
     Lock,
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let mut locks = Vec::new();
-    let mut keys = Vec::new();
-
-    for schematic in input.split("\n\n") {
-        let schematic_type = match schematic.lines().next().unwrap().chars().next().unwrap() {
-            '#' => SchematicType::Lock,
-            '.' => SchematicType::Key,
-            _ => panic!("Invalid schematic type"),
+#[derive(Debug)]
+struct Schematic {
+    kind: SchematicType,
+    heights: [u8; WIDTH],
+    /// One bit per `#` cell in row-major order, so a lock and key fit when they share no bits.
+    mask: u64,
+}
+
+impl Schematic {
+    fn fits(&self, other: &Schematic) -> bool {
+        self.mask & other.mask == 0
+    }
+}
+
+/// A schematic block that could not be parsed, with the 1-based input line the problem is on.
+#[derive(Debug, PartialEq, Eq)]
+struct MalformedSchematic {
+    line: usize,
+    reason: String,
+}
+
+impl fmt::Display for MalformedSchematic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+fn malformed(line: usize, reason: impl Into<String>) -> MalformedSchematic {
+    MalformedSchematic {
+        line,
+        reason: reason.into(),
+    }
+}
+
+/// Parses one block of rows, the first of which is on input line `first_line`.
+fn parse_schematic(first_line: usize, rows: &[&str]) -> Result<Schematic, MalformedSchematic> {
+    if rows.len() != HEIGHT {
+        return Err(malformed(
+            first_line,
+            format!("expected {HEIGHT} rows, found {}", rows.len()),
+        ));
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        if let Some(c) = row.chars().find(|&c| c != '#' && c != '.') {
+            return Err(malformed(
+                first_line + i,
+                format!("unexpected character {c:?}"),
+            ));
+        }
+        if row.len() != WIDTH {
+            return Err(malformed(
+                first_line + i,
+                format!("expected {WIDTH} columns, found {}", row.len()),
+            ));
+        }
+    }
+
+    let (kind, filled) = match rows[0] {
+        "#####" => (SchematicType::Lock, b'#'),
+        "....." => (SchematicType::Key, b'.'),
+        _ => {
+            return Err(malformed(
+                first_line,
+                "top row must be all '#' for a lock or all '.' for a key",
+            ))
+        }
+    };
+
+    let mut heights = [0; WIDTH];
+    let mut mask = 0;
+    for (x, height) in heights.iter_mut().enumerate() {
+        // A column reads `filled` from the top, then switches to the other character for good
+        let mut switched_at = None;
+        for (y, row) in rows.iter().enumerate() {
+            let c = row.as_bytes()[x];
+            if c == b'#' {
+                mask |= 1 << (y * WIDTH + x);
+            }
+            match switched_at {
+                None if c != filled => switched_at = Some(y),
+                Some(_) if c == filled => {
+                    return Err(malformed(
+                        first_line + y,
+                        format!("column {} is not one solid pin", x + 1),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        let Some(switched_at) = switched_at else {
+            return Err(malformed(
+                first_line + HEIGHT - 1,
+                format!("column {} fills the whole schematic", x + 1),
+            ));
         };
 
-        let columns = (0..5)
-            .map(|i| {
-                schematic
-                    .lines()
-                    .filter(|l| l.chars().nth(i).unwrap() == '#')
-                    .count()
-                    - 1
-            })
-            .collect::<Vec<_>>();
+        *height = match kind {
+            SchematicType::Lock => switched_at - 1,
+            SchematicType::Key => HEIGHT - switched_at - 1,
+        } as u8;
+    }
 
-        match schematic_type {
-            SchematicType::Lock => locks.push(columns),
-            SchematicType::Key => keys.push(columns),
+    Ok(Schematic {
+        kind,
+        heights,
+        mask,
+    })
+}
+
+/// Parses every schematic, or reports every malformed block instead of guessing at them.
+fn parse(input: &str) -> Result<Vec<Schematic>, Vec<MalformedSchematic>> {
+    let mut blocks: Vec<(usize, Vec<&str>)> = Vec::new();
+    let mut in_block = false;
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            in_block = false;
+        } else if in_block {
+            blocks.last_mut().unwrap().1.push(line);
+        } else {
+            blocks.push((i + 1, vec![line]));
+            in_block = true;
         }
     }
 
-    let mut fits = 0;
+    let (schematics, errors): (Vec<_>, Vec<_>) = blocks
+        .iter()
+        .map(|(first_line, rows)| parse_schematic(*first_line, rows))
+        .partition(Result::is_ok);
+
+    if errors.is_empty() {
+        Ok(schematics.into_iter().map(Result::unwrap).collect())
+    } else {
+        Err(errors.into_iter().map(Result::unwrap_err).collect())
+    }
+}
+
+/// Position of a height profile in a dense table, reading the columns as base-6 digits.
+fn profile_index(heights: [u8; WIDTH]) -> usize {
+    heights
+        .iter()
+        .rev()
+        .fold(0, |index, &h| index * (MAX_PIN as usize + 1) + h as usize)
+}
 
-    for lock in locks.iter() {
-        for key in keys.iter() {
-            if (0..5).all(|i| lock[i] + key[i] <= 5) {
-                fits += 1;
+/// Counts the lock and key pairs that fit without comparing every pair.
+///
+/// Keys are tallied by height profile, and the tally is turned into a prefix sum over all five
+/// columns, so each entry holds the number of keys no taller than that profile in any column.
+/// A lock then fits exactly the keys counted at its complementary profile.
+fn count_fits(schematics: &[Schematic]) -> usize {
+    let mut keys = vec![0; PROFILES];
+    for key in schematics.iter().filter(|s| s.kind == SchematicType::Key) {
+        keys[profile_index(key.heights)] += 1;
+    }
+
+    let mut stride = 1;
+    for _ in 0..WIDTH {
+        for i in 0..PROFILES {
+            if (i / stride) % (MAX_PIN as usize + 1) > 0 {
+                keys[i] += keys[i - stride];
             }
         }
+        stride *= MAX_PIN as usize + 1;
     }
 
-    Some(fits)
+    schematics
+        .iter()
+        .filter(|s| s.kind == SchematicType::Lock)
+        .map(|lock| keys[profile_index(lock.heights.map(|h| MAX_PIN - h))])
+        .sum()
+}
+
+#[allow(dead_code)]
+fn draw_fits(schematics: &[Schematic]) {
+    let (locks, keys): (Vec<_>, Vec<_>) = schematics
+        .iter()
+        .partition(|s| s.kind == SchematicType::Lock);
+
+    for lock in &locks {
+        for key in keys.iter().filter(|key| lock.fits(key)) {
+            println!("lock {:?} fits key {:?}", lock.heights, key.heights);
+        }
+    }
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    let schematics = match parse(input) {
+        Ok(schematics) => schematics,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("Malformed schematic at {error}");
+            }
+            return None;
+        }
+    };
+
+    //draw_fits(&schematics);
+
+    Some(count_fits(&schematics) as u32)
 }
 
 pub fn part_two(_input: &str) -> Option<u32> {
@@ -53,10 +227,150 @@ pub fn part_two(_input: &str) -> Option<u32> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code::random::Lcg;
 
     #[test]
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(3));
     }
+
+    #[test]
+    fn test_parse_heights() {
+        let schematics = parse(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+
+        assert_eq!(schematics[0].kind, SchematicType::Lock);
+        assert_eq!(schematics[0].heights, [0, 5, 3, 4, 3]);
+        assert_eq!(schematics[2].kind, SchematicType::Key);
+        assert_eq!(schematics[2].heights, [5, 0, 2, 1, 3]);
+    }
+
+    /// A schematic with the given kind and heights, in puzzle input form.
+    fn render(kind: SchematicType, heights: [u8; WIDTH]) -> String {
+        (0..HEIGHT)
+            .map(|y| {
+                heights
+                    .iter()
+                    .map(|&h| {
+                        let filled = match kind {
+                            SchematicType::Lock => y <= h as usize,
+                            SchematicType::Key => y >= HEIGHT - 1 - h as usize,
+                        };
+                        if filled {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_bucketed_count_matches_pairwise() {
+        let mut rng = Lcg::new(0x25);
+        let mut random = |n: u64| rng.below(n);
+
+        let blocks: Vec<String> = (0..400)
+            .map(|_| {
+                let kind = if random(2) == 0 {
+                    SchematicType::Lock
+                } else {
+                    SchematicType::Key
+                };
+                render(kind, [(); WIDTH].map(|_| random(MAX_PIN as u64 + 1) as u8))
+            })
+            .collect();
+        let schematics = parse(&blocks.join("\n\n")).unwrap();
+
+        let (locks, keys): (Vec<_>, Vec<_>) = schematics
+            .iter()
+            .partition(|s| s.kind == SchematicType::Lock);
+        let mut pairwise = 0;
+        for lock in &locks {
+            for key in &keys {
+                let by_heights = (0..WIDTH).all(|i| lock.heights[i] + key.heights[i] <= MAX_PIN);
+                assert_eq!(lock.fits(key), by_heights);
+                pairwise += usize::from(by_heights);
+            }
+        }
+
+        assert!(pairwise > 0);
+        assert_eq!(count_fits(&schematics), pairwise);
+    }
+
+    #[test]
+    fn test_reports_malformed_blocks() {
+        let input = "\
+#####
+.####
+.##.#
+.#.##
+.#...
+.#...
+.....
+
+.....
+#....
+.....
+#...#
+#.#.#
+#.###
+#####
+
+#####
+.###
+.....
+
+#####
+.####
+.###x
+.....
+.....
+.....
+.....
+
+##.##
+.....
+.....
+.....
+.....
+.....
+.....
+";
+        let errors = parse(input).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                malformed(4, "column 4 is not one solid pin"),
+                malformed(11, "column 1 is not one solid pin"),
+                malformed(17, "expected 7 rows, found 3"),
+                malformed(23, "unexpected character 'x'"),
+                malformed(
+                    29,
+                    "top row must be all '#' for a lock or all '.' for a key"
+                ),
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "line 4: column 4 is not one solid pin"
+        );
+        assert_eq!(part_one(input), None);
+    }
+
+    #[test]
+    fn test_reports_short_rows_and_full_columns() {
+        let errors = parse("#####\n#####\n####\n.....\n.....\n.....\n.....").unwrap_err();
+        assert_eq!(errors, vec![malformed(3, "expected 5 columns, found 4")]);
+
+        let errors = parse("#####\n#....\n#....\n#....\n#....\n#....\n#....").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![malformed(7, "column 1 fills the whole schematic")]
+        );
+    }
 }
@@ -0,0 +1,330 @@
+/// A fixed-capacity set of node indices backed by 64-bit words.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    /// Creates an empty set able to hold indices in `0..capacity`.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            words: vec![0; capacity.div_ceil(64)],
+        }
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.words[index / 64] &= !(1 << (index % 64));
+    }
+
+    #[must_use]
+    pub fn contains(&self, index: usize) -> bool {
+        self.words
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & b)
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a | b)
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & !b)
+                .collect(),
+        }
+    }
+
+    /// Number of elements shared with `other`, without allocating the intersection.
+    #[must_use]
+    pub fn intersection_len(&self, other: &Self) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterates over the contained indices in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+/// An undirected graph over nodes `0..len` stored as one adjacency bitset per node.
+#[derive(Clone, Debug)]
+pub struct Graph {
+    adjacency: Vec<BitSet>,
+}
+
+impl Graph {
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        Self {
+            adjacency: vec![BitSet::new(len); len],
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.adjacency.is_empty()
+    }
+
+    /// Adds an undirected edge. Self-loops are ignored.
+    pub fn add_edge(&mut self, a: usize, b: usize) {
+        if a != b {
+            self.adjacency[a].insert(b);
+            self.adjacency[b].insert(a);
+        }
+    }
+
+    #[must_use]
+    pub fn has_edge(&self, a: usize, b: usize) -> bool {
+        self.adjacency[a].contains(b)
+    }
+
+    #[must_use]
+    pub fn neighbours(&self, node: usize) -> &BitSet {
+        &self.adjacency[node]
+    }
+
+    /// Enumerates every triangle exactly once, as node indices in ascending order.
+    pub fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        (0..self.len()).flat_map(move |a| {
+            self.adjacency[a]
+                .iter()
+                .filter(move |&b| b > a)
+                .flat_map(move |b| {
+                    self.adjacency[a]
+                        .intersection(&self.adjacency[b])
+                        .iter()
+                        .filter(|&c| c > b)
+                        .map(|c| [a, b, c])
+                        .collect::<Vec<_>>()
+                })
+        })
+    }
+
+    /// Calls `visit` with every maximal clique, using Bron–Kerbosch with pivoting.
+    pub fn for_each_maximal_clique(&self, mut visit: impl FnMut(&[usize])) {
+        let mut candidates = BitSet::new(self.len());
+        for node in 0..self.len() {
+            candidates.insert(node);
+        }
+
+        let mut clique = Vec::new();
+        self.bron_kerbosch(&mut clique, candidates, BitSet::new(self.len()), &mut visit);
+    }
+
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<usize>,
+        mut candidates: BitSet,
+        mut excluded: BitSet,
+        visit: &mut impl FnMut(&[usize]),
+    ) {
+        if candidates.is_empty() {
+            if excluded.is_empty() {
+                visit(clique);
+            }
+            return;
+        }
+
+        // Pick the pivot that leaves the fewest candidates to branch on
+        let pivot = candidates
+            .union(&excluded)
+            .iter()
+            .max_by_key(|&u| candidates.intersection_len(&self.adjacency[u]))
+            .unwrap();
+
+        for node in candidates.difference(&self.adjacency[pivot]).iter() {
+            let neighbours = &self.adjacency[node];
+
+            clique.push(node);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(neighbours),
+                excluded.intersection(neighbours),
+                visit,
+            );
+            clique.pop();
+
+            candidates.remove(node);
+            excluded.insert(node);
+        }
+    }
+
+    /// Returns a largest clique, with node indices in ascending order.
+    #[must_use]
+    pub fn maximum_clique(&self) -> Vec<usize> {
+        let mut best = Vec::new();
+        self.for_each_maximal_clique(|clique| {
+            if clique.len() > best.len() {
+                best = clique.to_vec();
+            }
+        });
+
+        best.sort_unstable();
+        best
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{BitSet, Graph};
+    use crate::random::Lcg;
+
+    fn graph_from_edges(len: usize, edges: &[(usize, usize)]) -> Graph {
+        let mut graph = Graph::new(len);
+        for &(a, b) in edges {
+            graph.add_edge(a, b);
+        }
+        graph
+    }
+
+    fn is_clique(graph: &Graph, nodes: &[usize]) -> bool {
+        nodes
+            .iter()
+            .enumerate()
+            .all(|(i, &a)| nodes[i + 1..].iter().all(|&b| graph.has_edge(a, b)))
+    }
+
+    fn brute_force_clique_size(graph: &Graph) -> usize {
+        (0u32..1 << graph.len())
+            .filter_map(|mask| {
+                let nodes: Vec<usize> =
+                    (0..graph.len()).filter(|&i| mask & (1 << i) != 0).collect();
+                is_clique(graph, &nodes).then_some(nodes.len())
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn bitset_iterates_across_words() {
+        let mut set = BitSet::new(200);
+        set.insert(3);
+        set.insert(64);
+        set.insert(199);
+        set.remove(3);
+
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![64, 199]);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(199));
+        assert!(!set.contains(3));
+    }
+
+    #[test]
+    fn enumerates_each_triangle_once() {
+        // Two triangles sharing the edge 1-2, plus a pendant node
+        let graph = graph_from_edges(5, &[(0, 1), (0, 2), (1, 2), (1, 3), (2, 3), (3, 4)]);
+
+        assert_eq!(
+            graph.triangles().collect::<Vec<_>>(),
+            vec![[0, 1, 2], [1, 2, 3]]
+        );
+    }
+
+    #[test]
+    fn finds_clique_missed_by_greedy_growth() {
+        // Each clique member's first neighbour is a decoy outside the clique, so growing a
+        // party greedily from any node in adjacency order never finds {4, 5, 6, 7}.
+        let graph = graph_from_edges(
+            8,
+            &[
+                (4, 0),
+                (5, 1),
+                (6, 2),
+                (7, 3),
+                (0, 1),
+                (2, 3),
+                (4, 5),
+                (4, 6),
+                (4, 7),
+                (5, 6),
+                (5, 7),
+                (6, 7),
+            ],
+        );
+
+        assert_eq!(graph.maximum_clique(), vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn matches_brute_force_on_small_graphs() {
+        let mut rng = Lcg::new(0x2024);
+        for _ in 0..50 {
+            let mut graph = Graph::new(10);
+            for a in 0..10 {
+                for b in a + 1..10 {
+                    if rng.below(4) != 0 {
+                        graph.add_edge(a, b);
+                    }
+                }
+            }
+
+            let clique = graph.maximum_clique();
+            assert!(is_clique(&graph, &clique));
+            assert_eq!(clique.len(), brute_force_clique_size(&graph));
+        }
+    }
+}
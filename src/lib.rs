@@ -1,3 +1,8 @@
+pub mod animation;
+pub mod disjoint_set;
+pub mod graph;
+pub mod linear;
+pub mod random;
 pub mod template;
 
 // Use this file to add helper functions and additional modules.
@@ -0,0 +1,278 @@
+//! Exact integer linear algebra. Everything is computed in `i128`, so `i64` inputs never overflow.
+
+/// Returns `(g, x, y)` with `a * x + b * y == g`, where `g` is the non-negative gcd of `a` and `b`.
+#[must_use]
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// A fraction in lowest terms with a positive denominator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rational {
+    pub num: i128,
+    pub den: i128,
+}
+
+impl Rational {
+    /// # Panics
+    ///
+    /// Panics if `den` is zero.
+    #[must_use]
+    pub fn new(num: i128, den: i128) -> Self {
+        assert!(den != 0, "Rational with a zero denominator");
+
+        let (g, _, _) = extended_gcd(num, den);
+        let sign = den.signum();
+        Self {
+            num: sign * num / g,
+            den: sign * den / g,
+        }
+    }
+
+    /// The value as an integer, or `None` if it is not a whole number.
+    #[must_use]
+    pub fn to_integer(self) -> Option<i128> {
+        (self.den == 1).then_some(self.num)
+    }
+}
+
+/// The solutions of the system `a[i] * x + b[i] * y == c[i]` for `i` in `0..2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Solution2x2 {
+    /// Exactly one point, which may not be integral.
+    Unique(Rational, Rational),
+    /// The equations are multiples of each other, so every point on `a * x + b * y == c` solves
+    /// both. A system of two `0 == 0` rows is the line with every coefficient zero.
+    Line { a: i64, b: i64, c: i64 },
+    /// The equations contradict each other.
+    None,
+}
+
+/// Solves a 2x2 linear system exactly with Cramer's rule.
+#[must_use]
+pub fn solve_2x2(a: [i64; 2], b: [i64; 2], c: [i64; 2]) -> Solution2x2 {
+    let [a0, a1] = a.map(i128::from);
+    let [b0, b1] = b.map(i128::from);
+    let [c0, c1] = c.map(i128::from);
+
+    let det = a0 * b1 - a1 * b0;
+    if det != 0 {
+        return Solution2x2::Unique(
+            Rational::new(c0 * b1 - c1 * b0, det),
+            Rational::new(a0 * c1 - a1 * c0, det),
+        );
+    }
+
+    // The coefficient rows are parallel; the system is consistent only if the right-hand side
+    // is parallel to them too
+    if a0 * c1 != a1 * c0 || b0 * c1 != b1 * c0 {
+        return Solution2x2::None;
+    }
+
+    if a[0] != 0 || b[0] != 0 {
+        Solution2x2::Line {
+            a: a[0],
+            b: b[0],
+            c: c[0],
+        }
+    } else if a[1] != 0 || b[1] != 0 {
+        Solution2x2::Line {
+            a: a[1],
+            b: b[1],
+            c: c[1],
+        }
+    } else if c == [0, 0] {
+        Solution2x2::Line { a: 0, b: 0, c: 0 }
+    } else {
+        Solution2x2::None
+    }
+}
+
+/// The non-negative integer solution of `a * x + b * y == c` with the lowest
+/// `cost_a * x + cost_b * y`, or `None` if there is none.
+///
+/// # Panics
+///
+/// Panics if a coefficient or cost is negative.
+#[must_use]
+pub fn min_cost_nonnegative(
+    a: i64,
+    b: i64,
+    c: i64,
+    cost_a: i64,
+    cost_b: i64,
+) -> Option<(i64, i64)> {
+    assert!(
+        a >= 0 && b >= 0 && cost_a >= 0 && cost_b >= 0,
+        "Coefficients and costs must be non-negative"
+    );
+    if c < 0 {
+        return None;
+    }
+
+    // With a zero coefficient the other variable is fixed and this one is best left at zero
+    match (a, b) {
+        (0, 0) => return (c == 0).then_some((0, 0)),
+        (0, _) => return (c % b == 0).then_some((0, c / b)),
+        (_, 0) => return (c % a == 0).then_some((c / a, 0)),
+        _ => {}
+    }
+
+    let (a, b, c) = (i128::from(a), i128::from(b), i128::from(c));
+    let (g, s, _) = extended_gcd(a, b);
+    if c % g != 0 {
+        return None;
+    }
+    let (a, b, c) = (a / g, b / g, c / g);
+
+    // Every solution is (x0 + k * b, y0 - k * a); start from the one with the smallest x
+    let x0 = (s * c).rem_euclid(b);
+    let y0 = (c - a * x0) / b;
+    if y0 < 0 {
+        return None;
+    }
+
+    // Each step of k trades `a` of y for `b` of x, so only the two ends of the range can be best
+    let k = if i128::from(cost_a) * b < i128::from(cost_b) * a {
+        y0 / a
+    } else {
+        0
+    };
+
+    // Both lie between zero and the original `c`, so they fit back into an i64
+    let x = i64::try_from(x0 + k * b).unwrap();
+    let y = i64::try_from(y0 - k * a).unwrap();
+    Some((x, y))
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{extended_gcd, min_cost_nonnegative, solve_2x2, Rational, Solution2x2};
+    use crate::random::Lcg;
+
+    #[test]
+    fn finds_bezout_coefficients() {
+        for (a, b) in [
+            (240, 46),
+            (-240, 46),
+            (17, 0),
+            (0, -5),
+            (0, 0),
+            (i64::MAX as i128, 2),
+        ] {
+            let (g, x, y) = extended_gcd(a, b);
+            assert!(g >= 0);
+            assert_eq!(a * x + b * y, g, "{a} {b}");
+            if g != 0 {
+                assert_eq!((a % g, b % g), (0, 0));
+            }
+        }
+        assert_eq!(extended_gcd(240, 46).0, 2);
+    }
+
+    #[test]
+    fn normalises_rationals() {
+        assert_eq!(Rational::new(6, -4), Rational { num: -3, den: 2 });
+        assert_eq!(Rational::new(0, -7), Rational { num: 0, den: 1 });
+        assert_eq!(Rational::new(12, 4).to_integer(), Some(3));
+        assert_eq!(Rational::new(5, 4).to_integer(), None);
+    }
+
+    #[test]
+    fn solves_unique_systems() {
+        assert_eq!(
+            solve_2x2([94, 34], [22, 67], [8400, 5400]),
+            Solution2x2::Unique(Rational::new(80, 1), Rational::new(40, 1))
+        );
+        assert_eq!(
+            solve_2x2([2, 0], [0, 3], [1, 1]),
+            Solution2x2::Unique(Rational::new(1, 2), Rational::new(1, 3))
+        );
+
+        // Products of i64::MAX-sized values would overflow an i64 determinant
+        let big = i64::MAX;
+        assert_eq!(
+            solve_2x2([big, 0], [0, big], [big, big]),
+            Solution2x2::Unique(Rational::new(1, 1), Rational::new(1, 1))
+        );
+    }
+
+    #[test]
+    fn classifies_degenerate_systems() {
+        assert_eq!(
+            solve_2x2([1, 2], [3, 6], [5, 10]),
+            Solution2x2::Line { a: 1, b: 3, c: 5 }
+        );
+        assert_eq!(solve_2x2([1, 2], [3, 6], [5, 11]), Solution2x2::None);
+        assert_eq!(
+            solve_2x2([0, 4], [0, 2], [0, 8]),
+            Solution2x2::Line { a: 4, b: 2, c: 8 }
+        );
+        assert_eq!(
+            solve_2x2([0, 0], [0, 0], [0, 0]),
+            Solution2x2::Line { a: 0, b: 0, c: 0 }
+        );
+        assert_eq!(solve_2x2([0, 0], [0, 0], [0, 1]), Solution2x2::None);
+    }
+
+    /// Cheapest solution found by trying every small `x` and `y`.
+    fn brute_force(a: i64, b: i64, c: i64, cost_a: i64, cost_b: i64) -> Option<i64> {
+        (0..=c)
+            .flat_map(|x| (0..=c).map(move |y| (x, y)))
+            .filter(|&(x, y)| a * x + b * y == c)
+            .map(|(x, y)| cost_a * x + cost_b * y)
+            .min()
+    }
+
+    #[test]
+    fn matches_brute_force_min_cost() {
+        let mut rng = Lcg::new(0x13);
+        let mut random = |n: u64| rng.below(n) as i64;
+
+        for _ in 0..2000 {
+            let (a, b, c) = (random(8), random(8), random(40));
+            let (cost_a, cost_b) = (random(5), random(5));
+
+            let result = min_cost_nonnegative(a, b, c, cost_a, cost_b);
+            if let Some((x, y)) = result {
+                assert!(x >= 0 && y >= 0);
+                assert_eq!(a * x + b * y, c);
+            }
+            assert_eq!(
+                result.map(|(x, y)| cost_a * x + cost_b * y),
+                brute_force(a, b, c, cost_a, cost_b),
+                "{a}x + {b}y = {c} with costs {cost_a}, {cost_b}"
+            );
+        }
+    }
+
+    #[test]
+    fn handles_large_coefficients() {
+        let (a, b) = (1_000_000_007, 998_244_353);
+        let c = 3 * a + 5 * b;
+
+        assert_eq!(min_cost_nonnegative(a, b, c, 3, 1), Some((3, 5)));
+        assert_eq!(min_cost_nonnegative(a, b, c + 1, 3, 1), None);
+        assert_eq!(
+            min_cost_nonnegative(2, 4, i64::MAX - 1, 3, 1),
+            Some((1, (i64::MAX - 3) / 4))
+        );
+    }
+}
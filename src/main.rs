@@ -1,4 +1,4 @@
-use advent_of_code::template::commands::{all, download, read, scaffold, solve, time};
+use advent_of_code::template::commands::{all, download, read, readme, scaffold, solve, time};
 use args::{parse, AppArguments};
 
 #[cfg(feature = "today")]
@@ -7,6 +7,8 @@ use advent_of_code::template::Day;
 use std::process;
 
 mod args {
+    use advent_of_code::template::bench::{parse_duration_arg, parse_size, BenchOverrides};
+    use advent_of_code::template::commands::time::TimeOptions;
     use advent_of_code::template::Day;
     use std::process;
 
@@ -27,14 +29,22 @@ mod args {
             release: bool,
             dhat: bool,
             submit: Option<u8>,
+            bench: BenchOverrides,
+            threads: Option<usize>,
         },
         All {
             release: bool,
+            threads: Option<usize>,
+            jobs: usize,
+            limits: BenchOverrides,
         },
         Time {
             all: bool,
             day: Option<Day>,
-            store: bool,
+            options: TimeOptions,
+        },
+        Readme {
+            check: bool,
         },
         #[cfg(feature = "today")]
         Today,
@@ -46,17 +56,38 @@ mod args {
         let app_args = match args.subcommand()?.as_deref() {
             Some("all") => AppArguments::All {
                 release: args.contains("--release"),
+                threads: args.opt_value_from_str("--threads")?,
+                jobs: args.opt_value_from_str("--jobs")?.unwrap_or(1),
+                limits: parse_limits(&mut args, BenchOverrides::default())?,
             },
             Some("time") => {
                 let all = args.contains("--all");
-                let store = args.contains("--store");
+                let bench = parse_bench(&mut args)?;
+                let options = TimeOptions {
+                    store: args.contains("--store"),
+                    compare: args.contains("--compare"),
+                    threshold: args.opt_value_from_str("--threshold")?,
+                    export_dir: args.opt_value_from_str("--export")?,
+                    bench: parse_limits(&mut args, bench)?,
+                    threads: args.opt_value_from_str("--threads")?,
+                    single_threaded: args.contains("--single-threaded"),
+                };
+                if options.threshold.is_some() && !options.compare {
+                    eprintln!(
+                        "Unexpected command-line input. Format: cargo time --compare --threshold 5"
+                    );
+                    process::exit(1);
+                }
 
                 AppArguments::Time {
                     all,
                     day: args.opt_free_from_str()?,
-                    store,
+                    options,
                 }
             }
+            Some("readme") => AppArguments::Readme {
+                check: args.contains("--check"),
+            },
             Some("download") => AppArguments::Download {
                 day: args.free_from_str()?,
             },
@@ -73,6 +104,8 @@ mod args {
                 release: args.contains("--release"),
                 submit: args.opt_value_from_str("--submit")?,
                 dhat: args.contains("--dhat"),
+                bench: parse_bench(&mut args)?,
+                threads: args.opt_value_from_str("--threads")?,
             },
             #[cfg(feature = "today")]
             Some("today") => AppArguments::Today,
@@ -93,6 +126,28 @@ mod args {
 
         Ok(app_args)
     }
+
+    fn parse_bench(args: &mut pico_args::Arguments) -> Result<BenchOverrides, pico_args::Error> {
+        Ok(BenchOverrides {
+            budget: args.opt_value_from_fn("--budget", parse_duration_arg)?,
+            min_iterations: args.opt_value_from_str("--min-iterations")?,
+            max_iterations: args.opt_value_from_str("--max-iterations")?,
+            warmup: args.opt_value_from_str("--warmup")?,
+            total_budget: args.opt_value_from_fn("--total-budget", parse_duration_arg)?,
+            ..BenchOverrides::default()
+        })
+    }
+
+    fn parse_limits(
+        args: &mut pico_args::Arguments,
+        overrides: BenchOverrides,
+    ) -> Result<BenchOverrides, pico_args::Error> {
+        Ok(BenchOverrides {
+            timeout: args.opt_value_from_fn("--timeout", parse_duration_arg)?,
+            memory_limit: args.opt_value_from_fn("--memory-limit", parse_size)?,
+            ..overrides
+        })
+    }
 }
 
 fn main() {
@@ -102,8 +157,14 @@ fn main() {
             std::process::exit(1);
         }
         Ok(args) => match args {
-            AppArguments::All { release } => all::handle(release),
-            AppArguments::Time { day, all, store } => time::handle(day, all, store),
+            AppArguments::All {
+                release,
+                threads,
+                jobs,
+                limits,
+            } => all::handle(release, threads, jobs, limits),
+            AppArguments::Time { day, all, options } => time::handle(day, all, &options),
+            AppArguments::Readme { check } => readme::handle(check),
             AppArguments::Download { day } => download::handle(day),
             AppArguments::Read { day } => read::handle(day),
             AppArguments::Scaffold { day, download, overwrite } => {
@@ -117,7 +178,9 @@ fn main() {
                 release,
                 dhat,
                 submit,
-            } => solve::handle(day, release, dhat, submit),
+                bench,
+                threads,
+            } => solve::handle(day, release, dhat, submit, &bench, threads),
             #[cfg(feature = "today")]
             AppArguments::Today => {
                 match Day::today() {
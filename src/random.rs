@@ -0,0 +1,21 @@
+//! A small deterministic generator for test inputs. The day binaries link the library without
+//! `cfg(test)`, so this is a regular module rather than a test-only one.
+
+/// A 64-bit linear congruential generator; the same seed always yields the same sequence.
+#[derive(Clone, Debug)]
+pub struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns a value in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (self.state >> 33) % n
+    }
+}
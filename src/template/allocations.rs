@@ -0,0 +1,352 @@
+/// Heap allocation statistics per day, recorded from the summary dhat prints after each part.
+use std::{collections::HashMap, fs, io::Error, str::FromStr};
+use tinyjson::JsonValue;
+
+use crate::template::Day;
+
+static ALLOCATIONS_FILE_PATH: &str = "./data/allocations.json";
+
+/// Version of the JSON layout written by [`Allocations::store_file`].
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What dhat measured while a part ran once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocationStats {
+    pub total_bytes: u64,
+    pub total_blocks: u64,
+    /// Bytes live at the point of peak memory use.
+    pub peak_bytes: u64,
+}
+
+/// Represents allocation statistics for a single day.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DayAllocations {
+    pub day: Day,
+    pub part_1: Option<AllocationStats>,
+    pub part_2: Option<AllocationStats>,
+}
+
+impl DayAllocations {
+    pub fn part(&self, part: u8) -> Option<&AllocationStats> {
+        match part {
+            1 => self.part_1.as_ref(),
+            2 => self.part_2.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Represents allocation statistics for a set of days.
+/// Can be serialized from / to JSON.
+#[derive(Clone, Debug, Default)]
+pub struct Allocations {
+    pub data: Vec<DayAllocations>,
+}
+
+impl Allocations {
+    /// Dehydrate allocations to a JSON file.
+    pub fn store_file(&self) -> Result<(), Error> {
+        let json = JsonValue::from(self.clone());
+        let mut file = fs::File::create(ALLOCATIONS_FILE_PATH)?;
+        json.format_to(&mut file)
+    }
+
+    /// Rehydrate allocations from a JSON file. If not present, returns no allocations.
+    pub fn read_from_file() -> Self {
+        let Ok(s) = fs::read_to_string(ALLOCATIONS_FILE_PATH) else {
+            return Allocations::default();
+        };
+
+        match Allocations::try_from(s) {
+            Ok(allocations) => allocations,
+            Err(e) => {
+                eprintln!("{e}");
+                Allocations::default()
+            }
+        }
+    }
+
+    /// Merge two sets of allocations, overwriting `self` with `other` if present.
+    pub fn merge(&self, new: &Self) -> Self {
+        let mut data: Vec<DayAllocations> = new.data.clone();
+
+        for allocations in &self.data {
+            if !data.iter().any(|a| a.day == allocations.day) {
+                data.push(allocations.clone());
+            }
+        }
+
+        data.sort_unstable_by_key(|a| a.day);
+        Allocations { data }
+    }
+}
+
+/// Collects the dhat summaries from a solution's output. Each part prints its result on stdout
+/// and dhat prints a summary on stderr once the part's profiler is dropped, so the n-th summary
+/// belongs to the n-th part that ran.
+pub fn parse_dhat_output(stdout: &[String], stderr: &[String], day: Day) -> DayAllocations {
+    let parts = stdout.iter().filter_map(|line| {
+        if line.contains("Part 1:") {
+            Some(1)
+        } else if line.contains("Part 2:") {
+            Some(2)
+        } else {
+            None
+        }
+    });
+
+    let mut totals = stderr
+        .iter()
+        .filter_map(|line| line.strip_prefix("dhat: Total:"))
+        .map(parse_bytes_and_blocks);
+    let mut peaks = stderr
+        .iter()
+        .filter_map(|line| line.strip_prefix("dhat: At t-gmax:"))
+        .map(parse_bytes_and_blocks);
+
+    let mut allocations = DayAllocations {
+        day,
+        part_1: None,
+        part_2: None,
+    };
+
+    for part in parts {
+        let (Some(Some((total_bytes, total_blocks))), Some(Some((peak_bytes, _)))) =
+            (totals.next(), peaks.next())
+        else {
+            break;
+        };
+
+        let stats = Some(AllocationStats {
+            total_bytes,
+            total_blocks,
+            peak_bytes,
+        });
+        if part == 1 {
+            allocations.part_1 = stats;
+        } else {
+            allocations.part_2 = stats;
+        }
+    }
+
+    allocations
+}
+
+/// Parses `1,234 bytes in 5 blocks`.
+fn parse_bytes_and_blocks(s: &str) -> Option<(u64, u64)> {
+    let mut numbers = s
+        .split_whitespace()
+        .filter_map(|word| word.replace(',', "").parse().ok());
+    Some((numbers.next()?, numbers.next()?))
+}
+
+/* -------------------------------------------------------------------------- */
+
+impl From<Allocations> for JsonValue {
+    fn from(value: Allocations) -> Self {
+        let mut map: HashMap<String, JsonValue> = HashMap::new();
+
+        map.insert(
+            "version".into(),
+            JsonValue::Number(f64::from(SCHEMA_VERSION)),
+        );
+        map.insert(
+            "data".into(),
+            JsonValue::Array(value.data.iter().map(JsonValue::from).collect()),
+        );
+
+        JsonValue::Object(map)
+    }
+}
+
+impl TryFrom<String> for Allocations {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let json = JsonValue::from_str(&value).or(Err("not valid JSON file."))?;
+
+        let json_data = json
+            .get::<HashMap<String, JsonValue>>()
+            .ok_or("expected JSON document to be an object.")?
+            .get("data")
+            .ok_or("expected JSON document to have key `data`.")?
+            .get::<Vec<JsonValue>>()
+            .ok_or("expected `json.data` to be an array.")?;
+
+        Ok(Allocations {
+            data: json_data
+                .iter()
+                .map(DayAllocations::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+impl From<&DayAllocations> for JsonValue {
+    fn from(value: &DayAllocations) -> Self {
+        let mut map: HashMap<String, JsonValue> = HashMap::new();
+
+        map.insert("day".into(), JsonValue::String(value.day.to_string()));
+
+        for (key, part) in [("part_1", &value.part_1), ("part_2", &value.part_2)] {
+            let json = part.map_or(JsonValue::Null, |stats| {
+                JsonValue::Object(HashMap::from([
+                    (
+                        "total_bytes".into(),
+                        JsonValue::Number(stats.total_bytes as f64),
+                    ),
+                    (
+                        "total_blocks".into(),
+                        JsonValue::Number(stats.total_blocks as f64),
+                    ),
+                    (
+                        "peak_bytes".into(),
+                        JsonValue::Number(stats.peak_bytes as f64),
+                    ),
+                ]))
+            });
+            map.insert(key.into(), json);
+        }
+
+        JsonValue::Object(map)
+    }
+}
+
+impl TryFrom<&JsonValue> for DayAllocations {
+    type Error = String;
+
+    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+        let json = value
+            .get::<HashMap<String, JsonValue>>()
+            .ok_or("Expected allocations to be a JSON object.")?;
+
+        let day = json
+            .get("day")
+            .and_then(|v| v.get::<String>())
+            .and_then(|day| Day::from_str(day).ok())
+            .ok_or("Expected allocations.day to be a Day struct.")?;
+
+        let mut parts = [None, None];
+        for (part, key) in parts.iter_mut().zip(["part_1", "part_2"]) {
+            let Some(v) = json.get(key).filter(|v| !v.is_null()) else {
+                continue;
+            };
+            let stats = v.get::<HashMap<String, JsonValue>>().ok_or(format!(
+                "Expected allocations.{key} to be null or an object."
+            ))?;
+            let number = |field: &str| {
+                stats
+                    .get(field)
+                    .and_then(|v| v.get::<f64>())
+                    .map(|&n| n as u64)
+                    .ok_or(format!(
+                        "Expected allocations.{key}.{field} to be a number."
+                    ))
+            };
+
+            *part = Some(AllocationStats {
+                total_bytes: number("total_bytes")?,
+                total_blocks: number("total_blocks")?,
+                peak_bytes: number("peak_bytes")?,
+            });
+        }
+
+        let [part_1, part_2] = parts;
+        Ok(DayAllocations {
+            day,
+            part_1,
+            part_2,
+        })
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{parse_dhat_output, AllocationStats, Allocations, DayAllocations};
+    use crate::day;
+    use tinyjson::JsonValue;
+
+    #[test]
+    fn parses_dhat_summaries_per_part() {
+        let stdout = [
+            "Part 1: 42 (1.0ms)".to_string(),
+            "Part 2: 43 (2.0ms)".to_string(),
+        ];
+        let stderr = [
+            "dhat: Total:     1,234 bytes in 5 blocks",
+            "dhat: At t-gmax: 1,024 bytes in 1 blocks",
+            "dhat: At t-end:  0 bytes in 0 blocks",
+            "dhat: The data has been saved to dhat-heap.json, and is viewable with dhat/dh_view.html",
+            "dhat: Total:     10 bytes in 1 blocks",
+            "dhat: At t-gmax: 10 bytes in 1 blocks",
+        ]
+        .map(String::from);
+
+        let allocations = parse_dhat_output(&stdout, &stderr, day!(3));
+        assert_eq!(
+            allocations.part_1,
+            Some(AllocationStats {
+                total_bytes: 1234,
+                total_blocks: 5,
+                peak_bytes: 1024,
+            })
+        );
+        assert_eq!(allocations.part(2).unwrap().total_bytes, 10);
+    }
+
+    #[test]
+    fn assigns_summaries_to_parts_that_ran() {
+        let stdout = ["Part 2: 43 (2.0ms)".to_string()];
+        let stderr = [
+            "dhat: Total:     10 bytes in 1 blocks",
+            "dhat: At t-gmax: 10 bytes in 1 blocks",
+        ]
+        .map(String::from);
+
+        let allocations = parse_dhat_output(&stdout, &stderr, day!(3));
+        assert_eq!(allocations.part_1, None);
+        assert!(allocations.part_2.is_some());
+    }
+
+    #[test]
+    fn round_trips_allocations() {
+        let allocations = Allocations {
+            data: vec![DayAllocations {
+                day: day!(1),
+                part_1: Some(AllocationStats {
+                    total_bytes: 2048,
+                    total_blocks: 3,
+                    peak_bytes: 1024,
+                }),
+                part_2: None,
+            }],
+        };
+
+        let json = JsonValue::from(allocations.clone()).stringify().unwrap();
+        let parsed = Allocations::try_from(json).unwrap();
+        assert_eq!(parsed.data, allocations.data);
+    }
+
+    #[test]
+    fn merges_by_day() {
+        let day = |n| DayAllocations {
+            day: crate::template::Day::new(n).unwrap(),
+            part_1: None,
+            part_2: None,
+        };
+        let stored = Allocations {
+            data: vec![day(1), day(4)],
+        };
+        let new = Allocations {
+            data: vec![day(2), day(4)],
+        };
+
+        let merged = stored.merge(&new);
+        let days: Vec<_> = merged.data.iter().map(|a| a.day.into_inner()).collect();
+        assert_eq!(days, vec![1, 2, 4]);
+    }
+}
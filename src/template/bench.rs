@@ -0,0 +1,534 @@
+//! Settings for benchmarking solutions with `--time`.
+//!
+//! Settings are resolved from the defaults, then `bench.json` in the project root, then the
+//! command line, with later sources taking precedence. `bench.json` can override settings for
+//! all days and for single days:
+//!
+//! ```json
+//! {
+//!   "budget": "500ms",
+//!   "min_iterations": 10,
+//!   "max_iterations": 10000,
+//!   "warmup": 3,
+//!   "total_budget": "120s",
+//!   "timeout": "60s",
+//!   "memory_limit": "2GiB",
+//!   "days": { "09": { "budget": "5s", "max_iterations": 100, "timeout": "5m" } }
+//! }
+//! ```
+//!
+//! `timeout` and `memory_limit` apply to every run of a solution, timed or not, and stop a day
+//! that hangs or allocates without bound from stalling the rest of a multi-day run.
+//!
+//! Durations are written like `{:?}` prints them, e.g. `250µs`, `1.5ms` or `2s`, or in minutes
+//! and hours such as `5m` or `1h`. Sizes are bytes, optionally suffixed with `KiB`, `MiB` or `GiB`.
+//! An invalid `bench.json` is an error, rather than being ignored.
+use std::{collections::HashMap, fs, process, str::FromStr, time::Duration};
+use tinyjson::JsonValue;
+
+use crate::template::timings::parse_duration;
+use crate::template::Day;
+
+static BENCH_FILE_PATH: &str = "./bench.json";
+
+/// How long and how often a part is benchmarked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// Time to spend benchmarking a part, used to pick the number of iterations.
+    pub budget: Duration,
+    pub min_iterations: u64,
+    pub max_iterations: u64,
+    /// Untimed runs before measuring, e.g. to warm up caches.
+    pub warmup: u64,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            budget: Duration::from_secs(1),
+            min_iterations: 10,
+            max_iterations: 10000,
+            warmup: 0,
+        }
+    }
+}
+
+impl BenchConfig {
+    /// Settings for `day`, from the config file and then the command line.
+    pub fn resolve(file: &BenchFile, day: Day, cli: &BenchOverrides) -> Self {
+        let mut config = Self::default();
+
+        file.defaults.apply(&mut config);
+        if let Some(overrides) = file.days.get(&day) {
+            overrides.apply(&mut config);
+        }
+        cli.apply(&mut config);
+
+        // at least one sample is needed, and a lowered maximum also lowers the minimum.
+        config.max_iterations = config.max_iterations.max(1);
+        config.min_iterations = config.min_iterations.clamp(1, config.max_iterations);
+        config
+    }
+
+    /// Number of iterations that fit the budget, given the duration of a single run.
+    pub fn iterations(&self, base_time: Duration) -> u64 {
+        let iterations = self.budget.as_nanos() / base_time.as_nanos().max(10);
+        u64::try_from(iterations)
+            .unwrap_or(u64::MAX)
+            .clamp(self.min_iterations, self.max_iterations)
+    }
+}
+
+/// Settings given by the user. Unset settings keep their previous value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BenchOverrides {
+    pub budget: Option<Duration>,
+    pub min_iterations: Option<u64>,
+    pub max_iterations: Option<u64>,
+    pub warmup: Option<u64>,
+    /// Wall-clock cap for timing several days in one go.
+    pub total_budget: Option<Duration>,
+    /// Wall-clock cap for a single run of a solution.
+    pub timeout: Option<Duration>,
+    /// Cap on the address space of a solution, in bytes.
+    pub memory_limit: Option<u64>,
+}
+
+impl BenchOverrides {
+    fn apply(&self, config: &mut BenchConfig) {
+        if let Some(budget) = self.budget {
+            config.budget = budget;
+        }
+        if let Some(min_iterations) = self.min_iterations {
+            config.min_iterations = min_iterations;
+        }
+        if let Some(max_iterations) = self.max_iterations {
+            config.max_iterations = max_iterations;
+        }
+        if let Some(warmup) = self.warmup {
+            config.warmup = warmup;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Flags that forward the per-part settings to a solution binary.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![];
+
+        if let Some(budget) = self.budget {
+            args.extend(["--budget".into(), format_duration(budget)]);
+        }
+        for (flag, value) in [
+            ("--min-iterations", self.min_iterations),
+            ("--max-iterations", self.max_iterations),
+            ("--warmup", self.warmup),
+        ] {
+            if let Some(value) = value {
+                args.extend([flag.into(), value.to_string()]);
+            }
+        }
+
+        args
+    }
+
+    /// Reads the flags written by [`BenchOverrides::to_args`].
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let value = |flag: &str| {
+            let i = args.iter().position(|arg| arg == flag)?;
+            Some(
+                args.get(i + 1)
+                    .map(String::as_str)
+                    .ok_or(format!("missing value for `{flag}`.")),
+            )
+        };
+        let number = |flag: &str| {
+            value(flag)
+                .map(|v| {
+                    v?.parse()
+                        .map_err(|_| format!("invalid value for `{flag}`."))
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            budget: value("--budget")
+                .map(|v| parse_duration_arg(v?))
+                .transpose()?,
+            min_iterations: number("--min-iterations")?,
+            max_iterations: number("--max-iterations")?,
+            warmup: number("--warmup")?,
+            ..Self::default()
+        })
+    }
+}
+
+/// Limits a solution bin is run under, enforced by the process running it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RunLimits {
+    pub timeout: Option<Duration>,
+    pub memory_limit: Option<u64>,
+}
+
+impl RunLimits {
+    /// Limits for `day`, from the config file and then the command line.
+    pub fn resolve(file: &BenchFile, day: Day, cli: &BenchOverrides) -> Self {
+        let day_overrides = file.days.get(&day);
+        let sources = [Some(cli), day_overrides, Some(&file.defaults)];
+
+        Self {
+            timeout: sources.iter().flatten().find_map(|o| o.timeout),
+            memory_limit: sources.iter().flatten().find_map(|o| o.memory_limit),
+        }
+    }
+}
+
+/// Parses a duration such as `500ms` or `5m` given on the command line.
+pub fn parse_duration_arg(s: &str) -> Result<Duration, String> {
+    let nanos = match (s.strip_suffix('m'), s.strip_suffix('h')) {
+        (Some(minutes), _) => minutes.parse::<f64>().ok().map(|m| m * 60e9),
+        (_, Some(hours)) => hours.parse::<f64>().ok().map(|h| h * 3600e9),
+        _ => parse_duration(s),
+    };
+
+    nanos
+        .filter(|nanos| nanos.is_finite() && *nanos >= 0.0)
+        .map(|nanos| Duration::from_nanos(nanos as u64))
+        .ok_or(format!(
+            "invalid duration `{s}`, expected e.g. `500ms`, `2s` or `5m`."
+        ))
+}
+
+/// Parses a size such as `512MiB` given on the command line.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit: u64 = match &s[digits.len()..] {
+        "" | "B" => 1,
+        "K" | "KiB" => 1 << 10,
+        "M" | "MiB" => 1 << 20,
+        "G" | "GiB" => 1 << 30,
+        _ => 0,
+    };
+
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|_| unit > 0)
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or(format!(
+            "invalid size `{s}`, expected e.g. `512MiB` or `2GiB`."
+        ))
+}
+
+/// Formats a size in bytes with the largest unit that divides it.
+pub fn format_size(bytes: u64) -> String {
+    match [(30, "GiB"), (20, "MiB"), (10, "KiB")]
+        .into_iter()
+        .find(|(shift, _)| bytes != 0 && bytes.is_multiple_of(1 << shift))
+    {
+        Some((shift, unit)) => format!("{}{unit}", bytes >> shift),
+        None => format!("{bytes}B"),
+    }
+}
+
+/// Formats a duration so that [`parse_duration_arg`] reads it back exactly.
+pub fn format_duration(duration: Duration) -> String {
+    format!("{}ns", duration.as_nanos())
+}
+
+/// The contents of `bench.json`.
+#[derive(Clone, Debug, Default)]
+pub struct BenchFile {
+    pub defaults: BenchOverrides,
+    pub days: HashMap<Day, BenchOverrides>,
+}
+
+impl BenchFile {
+    /// Read `bench.json`. If not present, no settings are overridden.
+    /// Exits if the file is invalid, as running with settings other than the ones written down
+    /// would be misleading.
+    pub fn read_from_file() -> Self {
+        let Ok(s) = fs::read_to_string(BENCH_FILE_PATH) else {
+            return Self::default();
+        };
+
+        match Self::try_from(s) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("{BENCH_FILE_PATH}: {e}");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+impl TryFrom<String> for BenchFile {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let json = JsonValue::from_str(&value).or(Err("not valid JSON file."))?;
+
+        let defaults = BenchOverrides::try_from(&json)?;
+
+        let days = match json
+            .get::<HashMap<String, JsonValue>>()
+            .and_then(|json| json.get("days"))
+        {
+            None => HashMap::new(),
+            Some(days) => days
+                .get::<HashMap<String, JsonValue>>()
+                .ok_or("expected `days` to be an object.")?
+                .iter()
+                .map(|(day, overrides)| {
+                    let day = Day::from_str(day)
+                        .map_err(|_| format!("expected `days.{day}` to be a day."))?;
+                    Ok((day, BenchOverrides::try_from(overrides)?))
+                })
+                .collect::<Result<_, String>>()?,
+        };
+
+        Ok(BenchFile { defaults, days })
+    }
+}
+
+impl TryFrom<&JsonValue> for BenchOverrides {
+    type Error = String;
+
+    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+        let json = value
+            .get::<HashMap<String, JsonValue>>()
+            .ok_or("expected bench settings to be an object.")?;
+
+        let duration = |key: &str| {
+            json.get(key)
+                .map(|v| {
+                    let s = v
+                        .get::<String>()
+                        .ok_or(format!("expected `{key}` to be a duration string."))?;
+                    parse_duration_arg(s)
+                })
+                .transpose()
+        };
+        let number = |key: &str| {
+            json.get(key)
+                .map(|v| {
+                    v.get::<f64>()
+                        .filter(|n| n.fract() == 0.0 && **n >= 0.0)
+                        .map(|&n| n as u64)
+                        .ok_or(format!("expected `{key}` to be a whole number."))
+                })
+                .transpose()
+        };
+
+        Ok(BenchOverrides {
+            budget: duration("budget")?,
+            min_iterations: number("min_iterations")?,
+            max_iterations: number("max_iterations")?,
+            warmup: number("warmup")?,
+            total_budget: duration("total_budget")?,
+            timeout: duration("timeout")?,
+            memory_limit: json
+                .get("memory_limit")
+                .map(|v| match v {
+                    JsonValue::String(s) => parse_size(s),
+                    JsonValue::Number(n) if n.fract() == 0.0 && *n >= 0.0 => Ok(*n as u64),
+                    _ => Err("expected `memory_limit` to be a size.".into()),
+                })
+                .transpose()?,
+        })
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{
+        format_size, parse_duration_arg, parse_size, BenchConfig, BenchFile, BenchOverrides,
+        RunLimits,
+    };
+    use crate::day;
+    use std::time::Duration;
+
+    #[test]
+    fn resolves_in_order_of_precedence() {
+        let file = BenchFile::try_from(
+            r#"{ "budget": "500ms", "warmup": 2, "total_budget": "60s",
+                 "days": { "09": { "budget": "5s", "max_iterations": 50 } } }"#
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(file.defaults.total_budget, Some(Duration::from_secs(60)));
+
+        let config = BenchConfig::resolve(&file, day!(1), &BenchOverrides::default());
+        assert_eq!(config.budget, Duration::from_millis(500));
+        assert_eq!(config.warmup, 2);
+        assert_eq!(config.max_iterations, 10000);
+
+        let config = BenchConfig::resolve(&file, day!(9), &BenchOverrides::default());
+        assert_eq!(config.budget, Duration::from_secs(5));
+        assert_eq!(config.max_iterations, 50);
+
+        let cli = BenchOverrides {
+            max_iterations: Some(20),
+            ..BenchOverrides::default()
+        };
+        let config = BenchConfig::resolve(&file, day!(9), &cli);
+        assert_eq!(config.budget, Duration::from_secs(5));
+        assert_eq!(config.max_iterations, 20);
+    }
+
+    #[test]
+    fn keeps_iteration_bounds_consistent() {
+        let cli = BenchOverrides {
+            min_iterations: Some(0),
+            max_iterations: Some(0),
+            ..BenchOverrides::default()
+        };
+        let config = BenchConfig::resolve(&BenchFile::default(), day!(1), &cli);
+        assert_eq!((config.min_iterations, config.max_iterations), (1, 1));
+
+        let cli = BenchOverrides {
+            min_iterations: Some(100),
+            max_iterations: Some(10),
+            ..BenchOverrides::default()
+        };
+        let config = BenchConfig::resolve(&BenchFile::default(), day!(1), &cli);
+        assert_eq!(config.iterations(Duration::from_secs(1)), 10);
+
+        let cli = BenchOverrides {
+            max_iterations: Some(5),
+            ..BenchOverrides::default()
+        };
+        let config = BenchConfig::resolve(&BenchFile::default(), day!(1), &cli);
+        assert_eq!((config.min_iterations, config.max_iterations), (5, 5));
+    }
+
+    #[test]
+    fn fits_iterations_to_budget() {
+        let config = BenchConfig::default();
+        assert_eq!(config.iterations(Duration::from_millis(10)), 100);
+        assert_eq!(config.iterations(Duration::from_secs(5)), 10);
+        assert_eq!(config.iterations(Duration::ZERO), 10000);
+    }
+
+    #[test]
+    fn forwards_overrides_as_arguments() {
+        let overrides = BenchOverrides {
+            budget: Some(Duration::from_micros(1500)),
+            min_iterations: None,
+            max_iterations: Some(30),
+            warmup: Some(3),
+            total_budget: Some(Duration::from_secs(1)),
+            timeout: Some(Duration::from_secs(10)),
+            memory_limit: Some(1024),
+        };
+        let args = overrides.to_args();
+        assert_eq!(
+            args,
+            [
+                "--budget",
+                "1500000ns",
+                "--max-iterations",
+                "30",
+                "--warmup",
+                "3"
+            ]
+        );
+
+        let mut args_with_others = vec!["--time".to_string()];
+        args_with_others.extend(args);
+        let parsed = BenchOverrides::from_args(&args_with_others).unwrap();
+        assert_eq!(
+            parsed,
+            BenchOverrides {
+                total_budget: None,
+                timeout: None,
+                memory_limit: None,
+                ..overrides
+            }
+        );
+    }
+
+    #[test]
+    fn resolves_run_limits() {
+        let file = BenchFile::try_from(
+            r#"{ "timeout": "60s", "memory_limit": "1GiB",
+                 "days": { "09": { "timeout": "5s" }, "10": { "timeout": "5m" } } }"#
+                .to_string(),
+        )
+        .unwrap();
+
+        let limits = RunLimits::resolve(&file, day!(9), &BenchOverrides::default());
+        assert_eq!(limits.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(limits.memory_limit, Some(1 << 30));
+
+        let cli = BenchOverrides {
+            timeout: Some(Duration::from_secs(1)),
+            ..BenchOverrides::default()
+        };
+        let limits = RunLimits::resolve(&file, day!(1), &cli);
+        assert_eq!(limits.timeout, Some(Duration::from_secs(1)));
+
+        let limits = RunLimits::resolve(&BenchFile::default(), day!(1), &BenchOverrides::default());
+        assert_eq!(limits, RunLimits::default());
+    }
+
+    #[test]
+    fn parses_documented_example() {
+        let file = BenchFile::try_from(
+            r#"{
+                "budget": "500ms",
+                "min_iterations": 10,
+                "max_iterations": 10000,
+                "warmup": 3,
+                "total_budget": "120s",
+                "timeout": "60s",
+                "memory_limit": "2GiB",
+                "days": { "09": { "budget": "5s", "max_iterations": 100, "timeout": "5m" } }
+            }"#
+            .to_string(),
+        )
+        .unwrap();
+
+        let limits = RunLimits::resolve(&file, day!(9), &BenchOverrides::default());
+        assert_eq!(limits.timeout, Some(Duration::from_secs(300)));
+        assert_eq!(limits.memory_limit, Some(2 << 30));
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration_arg("1.5ms"), Ok(Duration::from_micros(1500)));
+        assert_eq!(parse_duration_arg("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration_arg("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration_arg("0.5h"), Ok(Duration::from_secs(1800)));
+        assert!(parse_duration_arg("5 minutes").is_err());
+        assert!(parse_duration_arg("-1s").is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512MiB"), Ok(512 << 20));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert!(parse_size("2TiB").is_err());
+        assert!(parse_size("MiB").is_err());
+        assert_eq!(format_size(512 << 20), "512MiB");
+        assert_eq!(format_size(1536), "1536B");
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert!(BenchOverrides::from_args(&["--warmup".to_string()]).is_err());
+        assert!(BenchOverrides::from_args(&["--budget".into(), "soon".into()]).is_err());
+        assert!(BenchFile::try_from(r#"{ "warmup": 1.5 }"#.to_string()).is_err());
+        assert!(BenchFile::try_from(r#"{ "budget": 5 }"#.to_string()).is_err());
+        assert!(BenchFile::try_from(r#"{ "days": { "26": {} } }"#.to_string()).is_err());
+        assert!(BenchFile::try_from(r#"{ "memory_limit": true }"#.to_string()).is_err());
+    }
+}
@@ -1,5 +1,21 @@
-use crate::template::{all_days, run_multi::run_multi};
+use std::process;
 
-pub fn handle(is_release: bool) {
-    run_multi(&all_days().collect(), is_release, false);
+use crate::template::all_days;
+use crate::template::bench::BenchOverrides;
+use crate::template::run_multi::{run_multi, RunOptions};
+
+/// `limits` holds the run limits given on the command line, on top of those in `bench.json`.
+pub fn handle(is_release: bool, threads: Option<usize>, jobs: usize, limits: BenchOverrides) {
+    let options = RunOptions {
+        is_release,
+        bench: limits,
+        threads,
+        jobs,
+        ..RunOptions::default()
+    };
+
+    let summary = run_multi(&all_days().collect(), &options);
+    if !summary.failures.is_empty() {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,42 @@
+use std::process;
+
+use crate::template::report::{self, ReportData, Section};
+use crate::template::timings::Timings;
+
+pub fn handle(check: bool) {
+    let data = ReportData::collect(Timings::read_from_file());
+
+    if check {
+        match report::check(&data) {
+            Ok(stale) if stale.is_empty() => println!("README is up to date."),
+            Ok(stale) => {
+                eprintln!(
+                    "README is out of date: {}. Run `cargo readme` to update it.",
+                    list_sections(&stale)
+                );
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Failed to check README: {e}");
+                process::exit(1);
+            }
+        }
+    } else {
+        match report::update(&data) {
+            Ok(changed) if changed.is_empty() => println!("README is already up to date."),
+            Ok(changed) => println!("Updated {}.", list_sections(&changed)),
+            Err(e) => {
+                eprintln!("Failed to update README: {e}");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn list_sections(sections: &[Section]) -> String {
+    sections
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
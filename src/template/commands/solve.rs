@@ -1,8 +1,19 @@
-use std::process::{Command, Stdio};
+use std::io::{BufRead, BufReader};
+use std::process::{self, Command, Stdio};
+use std::thread;
 
+use crate::template::allocations::{parse_dhat_output, Allocations};
+use crate::template::bench::BenchOverrides;
 use crate::template::Day;
 
-pub fn handle(day: Day, release: bool, dhat: bool, submit_part: Option<u8>) {
+pub fn handle(
+    day: Day,
+    release: bool,
+    dhat: bool,
+    submit_part: Option<u8>,
+    bench: &BenchOverrides,
+    threads: Option<usize>,
+) {
     let mut cmd_args = vec!["run".to_string(), "--bin".to_string(), day.to_string()];
 
     if dhat {
@@ -23,6 +34,22 @@ pub fn handle(day: Day, release: bool, dhat: bool, submit_part: Option<u8>) {
         cmd_args.push(submit_part.to_string());
     }
 
+    if let Some(threads) = threads {
+        cmd_args.push("--threads".to_string());
+        cmd_args.push(threads.to_string());
+    }
+
+    // bench settings imply benching, as they would be ignored otherwise.
+    if !bench.is_empty() {
+        cmd_args.push("--time".to_string());
+        cmd_args.extend(bench.to_args());
+    }
+
+    if dhat {
+        run_with_dhat(day, &cmd_args);
+        return;
+    }
+
     let mut cmd = Command::new("cargo")
         .args(&cmd_args)
         .stdout(Stdio::inherit())
@@ -30,5 +57,56 @@ pub fn handle(day: Day, release: bool, dhat: bool, submit_part: Option<u8>) {
         .spawn()
         .unwrap();
 
-    cmd.wait().unwrap();
+    let status = cmd.wait().unwrap();
+    if !status.success() {
+        process::exit(status.code().unwrap_or(1));
+    }
+}
+
+/// Runs the solution while forwarding its output, then stores the allocation summary dhat
+/// printed for each part. Exits with the solution's exit code if it fails.
+fn run_with_dhat(day: Day, cmd_args: &[String]) {
+    let mut cmd = Command::new("cargo")
+        .args(cmd_args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let stdout = BufReader::new(cmd.stdout.take().unwrap());
+    let stderr = BufReader::new(cmd.stderr.take().unwrap());
+
+    let thread = thread::spawn(move || {
+        stderr
+            .lines()
+            .map(Result::unwrap)
+            .inspect(|line| eprintln!("{line}"))
+            .collect::<Vec<_>>()
+    });
+
+    let stdout_lines: Vec<String> = stdout
+        .lines()
+        .map(Result::unwrap)
+        .inspect(|line| println!("{line}"))
+        .collect();
+    let stderr_lines = thread.join().unwrap();
+
+    // a failed run leaves incomplete statistics, so none are stored.
+    let status = cmd.wait().unwrap();
+    if !status.success() {
+        process::exit(status.code().unwrap_or(1));
+    }
+
+    let allocations = parse_dhat_output(&stdout_lines, &stderr_lines, day);
+    if allocations.part_1.is_none() && allocations.part_2.is_none() {
+        return;
+    }
+
+    let merged = Allocations::read_from_file().merge(&Allocations {
+        data: vec![allocations],
+    });
+    match merged.store_file() {
+        Ok(()) => println!("Stored allocation statistics."),
+        Err(e) => eprintln!("Failed to store allocation statistics: {e}"),
+    }
 }
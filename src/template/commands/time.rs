@@ -1,10 +1,36 @@
 use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
 
-use crate::template::run_multi::run_multi;
+use crate::template::bench::BenchOverrides;
+use crate::template::export;
+use crate::template::history::{machine_name, HistoryEntry, TimingHistory};
+use crate::template::machine::MachineInfo;
+use crate::template::report::{self, ReportData};
+use crate::template::run_multi::{run_multi, RunOptions};
 use crate::template::timings::Timings;
-use crate::template::{all_days, readme_benchmarks, Day};
+use crate::template::{all_days, Day, ANSI_BOLD, ANSI_RESET};
 
-pub fn handle(day: Option<Day>, run_all: bool, store: bool) {
+/// Slowdown in percent that `--compare` tolerates before failing, unless `--threshold` is given.
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 10.0;
+
+/// Flags of the `time` command besides which days to run.
+#[derive(Clone, Debug, Default)]
+pub struct TimeOptions {
+    pub store: bool,
+    pub compare: bool,
+    /// Slowdown in percent tolerated by `compare`.
+    pub threshold: Option<f64>,
+    pub export_dir: Option<PathBuf>,
+    pub bench: BenchOverrides,
+    /// Size of the rayon pool, instead of one thread per core.
+    pub threads: Option<usize>,
+    /// Also time the days that use rayon with a single thread.
+    pub single_threaded: bool,
+}
+
+pub fn handle(day: Option<Day>, run_all: bool, options: &TimeOptions) {
     let stored_timings = Timings::read_from_file();
 
     let days_to_run = day.map_or_else(
@@ -21,20 +47,93 @@ pub fn handle(day: Option<Day>, run_all: bool, store: bool) {
         |day| HashSet::from([day]),
     );
 
-    let timings = run_multi(&days_to_run, true, true).unwrap();
+    let run_options = RunOptions {
+        is_release: true,
+        is_timed: true,
+        bench: options.bench.clone(),
+        threads: options.threads,
+        single_threaded: options.single_threaded,
+        jobs: 1,
+    };
+    let summary = run_multi(&days_to_run, &run_options);
+    let mut timings = summary.timings.unwrap();
+
+    timings.machine = Some(MachineInfo::capture(true, options.threads));
+
+    let mut has_regressions = false;
+    if options.compare {
+        let threshold = options.threshold.unwrap_or(DEFAULT_REGRESSION_THRESHOLD);
+        has_regressions = print_comparison(&timings, threshold);
+    }
 
-    if store {
+    if options.store {
         let merged_timings = stored_timings.merge(&timings);
         merged_timings.store_file().unwrap();
 
+        if let Err(e) = TimingHistory::append_to_file(&HistoryEntry::capture(timings.clone())) {
+            eprintln!("Failed to append to timing history: {e}");
+        }
+
         println!();
-        match readme_benchmarks::update(merged_timings) {
-            Ok(()) => {
+        match report::update(&ReportData::collect(merged_timings)) {
+            Ok(_) => {
                 println!("Stored updated benchmarks.");
             }
-            Err(_) => {
-                eprintln!("Failed to store updated benchmarks.");
+            Err(e) => {
+                eprintln!("Failed to store updated benchmarks: {e}");
             }
         }
     }
+
+    if let Some(dir) = &options.export_dir {
+        let mut history = TimingHistory::read_from_file();
+        if !options.store {
+            // include this run in the sparklines, as it was not appended to the history above.
+            history.entries.push(HistoryEntry::capture(timings.clone()));
+        }
+
+        match export::export(dir, &timings, &history, &machine_name()) {
+            Ok(()) => println!("Exported benchmark report to {}.", dir.display()),
+            Err(e) => eprintln!("Failed to export benchmark report: {e}"),
+        }
+    }
+
+    if has_regressions || !summary.failures.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// Print every part next to its last stored timing on this machine.
+/// Returns whether any part became slower by more than `threshold` percent.
+fn print_comparison(timings: &Timings, threshold: f64) -> bool {
+    let history = TimingHistory::read_from_file();
+    let comparisons = history.compare(&machine_name(), timings);
+
+    println!("\n{ANSI_BOLD}Compared to baseline{ANSI_RESET}");
+    if comparisons.is_empty() {
+        println!("No stored baseline for this machine, run with `--store` to record one.");
+        return false;
+    }
+
+    let mut regressions = 0;
+    for comparison in &comparisons {
+        let is_regression = comparison.is_regression(threshold / 100.0);
+        regressions += usize::from(is_regression);
+
+        println!(
+            "Day {} Part {}: {:.1?} → {:.1?} ({:+.1}%, baseline {}){}",
+            comparison.day,
+            comparison.part,
+            Duration::from_nanos(comparison.baseline_nanos as u64),
+            Duration::from_nanos(comparison.current_nanos as u64),
+            comparison.change() * 100.0,
+            comparison.baseline.label(),
+            if is_regression { " ▲ regression" } else { "" }
+        );
+    }
+
+    if regressions > 0 {
+        eprintln!("{regressions} part(s) regressed by more than {threshold}%.");
+    }
+    regressions > 0
 }
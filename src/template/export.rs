@@ -0,0 +1,278 @@
+/// Writes benchmark results to files that can be shared outside the repository: a CSV export of
+/// the timings and a self-contained HTML report with inline SVG charts.
+use std::{fmt::Write as _, fs, io::Error, path::Path, time::Duration};
+
+use crate::template::history::TimingHistory;
+use crate::template::timings::{PartTiming, Timings};
+use crate::template::Day;
+
+static CSV_FILE_NAME: &str = "timings.csv";
+static HTML_FILE_NAME: &str = "report.html";
+
+const CHART_WIDTH: f64 = 720.0;
+const CHART_LABEL_WIDTH: f64 = 110.0;
+const CHART_BAR_WIDTH: f64 = 480.0;
+const CHART_ROW_HEIGHT: f64 = 18.0;
+const CHART_AXIS_HEIGHT: f64 = 20.0;
+const SPARKLINE_WIDTH: f64 = 120.0;
+const SPARKLINE_HEIGHT: f64 = 24.0;
+
+/// Write `timings.csv` and `report.html` into `dir`, creating it if needed.
+/// Sparklines are drawn from the runs `history` holds for `machine`.
+pub fn export(
+    dir: &Path,
+    timings: &Timings,
+    history: &TimingHistory,
+    machine: &str,
+) -> Result<(), Error> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(CSV_FILE_NAME), to_csv(timings))?;
+    fs::write(dir.join(HTML_FILE_NAME), to_html(timings, history, machine))?;
+    Ok(())
+}
+
+/// One line per part that has a timing.
+pub fn to_csv(timings: &Timings) -> String {
+    let mut csv = String::from("day,part,nanos,samples,spread_nanos\n");
+
+    for (day, part, timing) in parts(timings) {
+        let _ = writeln!(
+            csv,
+            "{day},{part},{},{},{}",
+            timing.nanos, timing.samples, timing.spread_nanos
+        );
+    }
+
+    csv
+}
+
+fn parts(timings: &Timings) -> impl Iterator<Item = (Day, u8, &PartTiming)> {
+    timings.data.iter().flat_map(|timing| {
+        [1, 2]
+            .into_iter()
+            .filter_map(move |part| Some((timing.day, part, timing.part(part)?)))
+    })
+}
+
+/// A complete HTML document without any external resources.
+pub fn to_html(timings: &Timings, history: &TimingHistory, machine: &str) -> String {
+    let mut rows = String::new();
+
+    for (day, part, timing) in parts(timings) {
+        let _ = writeln!(
+            rows,
+            "<tr><td>Day {}</td><td>{part}</td><td>{:.1?}</td><td>±{:.1?}</td><td>{}</td><td>{}</td></tr>",
+            day.into_inner(),
+            timing.duration(),
+            timing.spread(),
+            timing.samples,
+            sparkline(&history.series(machine, day, part)),
+        );
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Benchmarks</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; color: #222; }}
+table {{ border-collapse: collapse; }}
+th, td {{ padding: 2px 12px; text-align: right; }}
+tr:nth-child(even) {{ background: #f4f4f4; }}
+</style>
+</head>
+<body>
+<h1>Benchmarks</h1>
+<p>Measured on <code>{}</code>. Total: {:.2}ms.</p>
+{}
+<h2>Parts</h2>
+<table>
+<tr><th>Day</th><th>Part</th><th>Mean</th><th>Spread</th><th>Samples</th><th>History</th></tr>
+{rows}</table>
+</body>
+</html>
+"#,
+        escape(machine),
+        timings.total_millis(),
+        bar_chart(timings),
+    )
+}
+
+/// Horizontal bars, one per part, on a logarithmic time axis spanning whole decades.
+fn bar_chart(timings: &Timings) -> String {
+    let bars: Vec<_> = parts(timings).collect();
+    if bars.is_empty() {
+        return String::new();
+    }
+
+    let (min, max) = bars
+        .iter()
+        .map(|(_, _, timing)| timing.nanos.max(1.0).log10())
+        .fold((f64::MAX, f64::MIN), |(min, max), x| {
+            (min.min(x), max.max(x))
+        });
+    let (from, to) = (min.floor(), max.ceil().max(min.floor() + 1.0));
+    let scale = |nanos: f64| (nanos.max(1.0).log10() - from) / (to - from) * CHART_BAR_WIDTH;
+
+    let height = bars.len() as f64 * CHART_ROW_HEIGHT + CHART_AXIS_HEIGHT;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{CHART_WIDTH}" height="{height}" font-size="11">"#
+    );
+    svg.push('\n');
+
+    for decade in from as i32..=to as i32 {
+        let x = CHART_LABEL_WIDTH + scale(10_f64.powi(decade));
+        let label = format!("{:?}", Duration::from_nanos(10_u64.pow(decade as u32)));
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{x:.1}" y1="0" x2="{x:.1}" y2="{:.1}" stroke="#ddd"/><text x="{x:.1}" y="{:.1}" text-anchor="middle" fill="#666">{label}</text>"##,
+            height - CHART_AXIS_HEIGHT,
+            height - 6.0,
+        );
+    }
+
+    for (i, (day, part, timing)) in bars.iter().enumerate() {
+        let y = i as f64 * CHART_ROW_HEIGHT;
+        let width = scale(timing.nanos);
+        let _ = writeln!(
+            svg,
+            r##"<text x="0" y="{:.1}">Day {} Part {part}</text><rect x="{CHART_LABEL_WIDTH}" y="{:.1}" width="{width:.1}" height="{:.1}" fill="#{}"/><text x="{:.1}" y="{:.1}">{:.1?}</text>"##,
+            y + 13.0,
+            day.into_inner(),
+            y + 3.0,
+            CHART_ROW_HEIGHT - 6.0,
+            if *part == 1 { "4e79a7" } else { "f28e2b" },
+            CHART_LABEL_WIDTH + width + 4.0,
+            y + 13.0,
+            timing.duration(),
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+/// A small line of a part's stored timings, oldest on the left, scaled to its own range.
+fn sparkline(values: &[f64]) -> String {
+    if values.len() < 2 {
+        return String::new();
+    }
+
+    let min = values.iter().copied().fold(f64::MAX, f64::min);
+    let max = values.iter().copied().fold(f64::MIN, f64::max);
+    let range = (max - min).max(f64::EPSILON);
+
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let x = i as f64 / (values.len() - 1) as f64 * SPARKLINE_WIDTH;
+            let y = SPARKLINE_HEIGHT - 2.0 - (value - min) / range * (SPARKLINE_HEIGHT - 4.0);
+            format!("{x:.1},{y:.1}")
+        })
+        .collect();
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{SPARKLINE_WIDTH}" height="{SPARKLINE_HEIGHT}"><polyline points="{}" fill="none" stroke="#4e79a7" stroke-width="1.5"/></svg>"##,
+        points.join(" ")
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{sparkline, to_csv, to_html};
+    use crate::{
+        day,
+        template::history::{HistoryEntry, TimingHistory},
+        template::timings::{PartTiming, Timing, Timings},
+    };
+
+    fn part(nanos: f64) -> PartTiming {
+        PartTiming {
+            nanos,
+            samples: 10,
+            spread_nanos: 5.0,
+        }
+    }
+
+    fn get_mock_timings() -> Timings {
+        Timings {
+            data: vec![
+                Timing {
+                    day: day!(1),
+                    part_1: Some(part(1_500.0)),
+                    part_2: Some(part(2_000_000.0)),
+                },
+                Timing {
+                    day: day!(2),
+                    part_1: Some(part(40.0)),
+                    part_2: None,
+                },
+            ],
+            machine: None,
+            single_threaded: vec![],
+        }
+    }
+
+    #[test]
+    fn exports_csv() {
+        assert_eq!(
+            to_csv(&get_mock_timings()),
+            [
+                "day,part,nanos,samples,spread_nanos",
+                "01,1,1500,10,5",
+                "01,2,2000000,10,5",
+                "02,1,40,10,5",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn renders_self_contained_html() {
+        let history = TimingHistory {
+            entries: [3_000.0, 1_500.0]
+                .map(|nanos| HistoryEntry {
+                    commit: None,
+                    timestamp: 0,
+                    machine: "<box>".into(),
+                    timings: Timings {
+                        data: vec![Timing {
+                            day: day!(1),
+                            part_1: Some(part(nanos)),
+                            part_2: None,
+                        }],
+                        machine: None,
+                        single_threaded: vec![],
+                    },
+                })
+                .into(),
+        };
+
+        let html = to_html(&get_mock_timings(), &history, "<box>");
+        assert!(html.contains("&lt;box&gt;"));
+        assert_eq!(html.matches("<rect").count(), 3);
+        assert_eq!(html.matches("<polyline").count(), 1);
+        assert!(!html.contains("src="));
+        assert!(!html.contains("<link"));
+    }
+
+    #[test]
+    fn draws_sparklines_across_full_range() {
+        assert_eq!(sparkline(&[1.0]), "");
+        let svg = sparkline(&[2.0, 1.0, 3.0]);
+        assert!(svg.contains(r#"points="0.0,12.0 60.0,22.0 120.0,2.0""#));
+    }
+}
@@ -0,0 +1,374 @@
+/// Append-only record of every stored benchmark run, used to spot performance regressions.
+/// Each line of the history file is one run, as a JSON object.
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, OpenOptions},
+    io::{Error, Write},
+    process::Command,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tinyjson::JsonValue;
+
+use crate::template::machine::command_output;
+use crate::template::timings::Timings;
+use crate::template::Day;
+
+static HISTORY_FILE_PATH: &str = "./data/timings_history.jsonl";
+
+/// A benchmark run, along with where and when it was taken.
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    /// Short hash of the checked out commit, suffixed with `-dirty` if there were local changes.
+    pub commit: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub machine: String,
+    pub timings: Timings,
+}
+
+impl HistoryEntry {
+    /// Wraps the timings of a run just taken on this machine.
+    pub fn capture(timings: Timings) -> Self {
+        Self {
+            commit: current_commit(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            machine: machine_name(),
+            timings,
+        }
+    }
+
+    /// A short label for the run, e.g. `1a2b3c4 on 2024-12-25`.
+    pub fn label(&self) -> String {
+        format!(
+            "{} on {}",
+            self.commit.as_deref().unwrap_or("unknown commit"),
+            format_date(self.timestamp)
+        )
+    }
+}
+
+/// How a part performed in this run compared to its most recent stored run.
+#[derive(Clone, Debug)]
+pub struct PartComparison<'a> {
+    pub day: Day,
+    pub part: u8,
+    pub baseline: &'a HistoryEntry,
+    pub baseline_nanos: f64,
+    pub current_nanos: f64,
+}
+
+impl PartComparison<'_> {
+    /// Relative change in duration, e.g. `0.25` for a part that became 25% slower.
+    pub fn change(&self) -> f64 {
+        self.current_nanos / self.baseline_nanos - 1.0
+    }
+
+    /// Whether the part became slower by more than `threshold`, given as a fraction.
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        self.change() > threshold
+    }
+}
+
+/// Every benchmark run stored so far, oldest first.
+#[derive(Clone, Debug, Default)]
+pub struct TimingHistory {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl TimingHistory {
+    /// Read the history file. Lines that cannot be parsed are reported and skipped, and a
+    /// missing file is an empty history.
+    pub fn read_from_file() -> Self {
+        let Ok(contents) = fs::read_to_string(HISTORY_FILE_PATH) else {
+            return Self::default();
+        };
+
+        let entries = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| match HistoryEntry::try_from(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    eprintln!("{HISTORY_FILE_PATH}:{}: {e}", i + 1);
+                    None
+                }
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    /// Append a run to the history file, leaving earlier runs untouched.
+    pub fn append_to_file(entry: &HistoryEntry) -> Result<(), Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(HISTORY_FILE_PATH)?;
+        let line = JsonValue::from(entry).stringify().map_err(Error::other)?;
+        writeln!(file, "{line}")
+    }
+
+    /// The most recent run on `machine` with a timing for this part, and that timing.
+    pub fn baseline(&self, machine: &str, day: Day, part: u8) -> Option<(&HistoryEntry, f64)> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.machine == machine)
+            .find_map(|entry| {
+                let timing = entry.timings.data.iter().find(|t| t.day == day)?;
+                Some((entry, timing.part(part)?.nanos))
+            })
+    }
+
+    /// Every stored timing of this part on `machine`, oldest first.
+    pub fn series(&self, machine: &str, day: Day, part: u8) -> Vec<f64> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.machine == machine)
+            .filter_map(|entry| {
+                let timing = entry.timings.data.iter().find(|t| t.day == day)?;
+                Some(timing.part(part)?.nanos)
+            })
+            .collect()
+    }
+
+    /// Compare every part of `current` that has a baseline on `machine`.
+    pub fn compare(&self, machine: &str, current: &Timings) -> Vec<PartComparison<'_>> {
+        current
+            .data
+            .iter()
+            .flat_map(|timing| [1, 2].map(|part| (timing, part)))
+            .filter_map(|(timing, part)| {
+                let current_nanos = timing.part(part)?.nanos;
+                let (baseline, baseline_nanos) = self.baseline(machine, timing.day, part)?;
+                Some(PartComparison {
+                    day: timing.day,
+                    part,
+                    baseline,
+                    baseline_nanos,
+                    current_nanos,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Name of the machine benchmarks are taken on, so runs on different hardware are not compared.
+pub fn machine_name() -> String {
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        // macOS sets neither variable and has no /etc/hostname
+        .or_else(|| command_output("scutil", &["--get", "ComputerName"]))
+        .or_else(|| command_output("hostname", &[]))
+        .unwrap_or_else(|| "unknown".into())
+}
+
+fn current_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_string();
+
+    let is_dirty = Command::new("git")
+        .args(["diff", "--quiet", "HEAD"])
+        .status()
+        .is_ok_and(|status| !status.success());
+
+    Some(if is_dirty {
+        format!("{hash}-dirty")
+    } else {
+        hash
+    })
+}
+
+/// Formats a Unix timestamp as a `YYYY-MM-DD` date in UTC.
+fn format_date(timestamp: u64) -> String {
+    // see: https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (timestamp / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/* -------------------------------------------------------------------------- */
+
+impl From<&HistoryEntry> for JsonValue {
+    fn from(value: &HistoryEntry) -> Self {
+        let mut map: HashMap<String, JsonValue> = HashMap::new();
+
+        map.insert(
+            "commit".into(),
+            value
+                .commit
+                .clone()
+                .map_or(JsonValue::Null, JsonValue::String),
+        );
+        map.insert(
+            "timestamp".into(),
+            JsonValue::Number(value.timestamp as f64),
+        );
+        map.insert("machine".into(), JsonValue::String(value.machine.clone()));
+        map.insert("timings".into(), JsonValue::from(value.timings.clone()));
+
+        JsonValue::Object(map)
+    }
+}
+
+impl TryFrom<&str> for HistoryEntry {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let json = JsonValue::from_str(value).or(Err("not valid JSON."))?;
+        let json = json
+            .get::<HashMap<String, JsonValue>>()
+            .ok_or("Expected history entry to be a JSON object.")?;
+
+        let commit = json
+            .get("commit")
+            .map(|v| if v.is_null() { None } else { v.get::<String>() })
+            .ok_or("Expected entry.commit to be null or string.")?;
+
+        let timestamp = json
+            .get("timestamp")
+            .and_then(|v| v.get::<f64>().copied())
+            .ok_or("Expected entry.timestamp to be a number.")?;
+
+        let machine = json
+            .get("machine")
+            .and_then(|v| v.get::<String>())
+            .ok_or("Expected entry.machine to be a string.")?;
+
+        let timings = json
+            .get("timings")
+            .ok_or("Expected entry to have key `timings`.")
+            .map(Timings::try_from)??;
+
+        Ok(HistoryEntry {
+            commit: commit.cloned(),
+            timestamp: timestamp as u64,
+            machine: machine.clone(),
+            timings,
+        })
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{format_date, HistoryEntry, TimingHistory};
+    use crate::{
+        day,
+        template::timings::{PartTiming, Timing, Timings},
+    };
+    use tinyjson::JsonValue;
+
+    fn part(millis: f64) -> PartTiming {
+        PartTiming {
+            nanos: millis * 1_000_000_f64,
+            samples: 10,
+            spread_nanos: 0_f64,
+        }
+    }
+
+    fn entry(commit: &str, machine: &str, part_1: f64, part_2: Option<f64>) -> HistoryEntry {
+        HistoryEntry {
+            commit: Some(commit.into()),
+            timestamp: 1_735_084_800,
+            machine: machine.into(),
+            timings: Timings {
+                data: vec![Timing {
+                    day: day!(1),
+                    part_1: Some(part(part_1)),
+                    part_2: part_2.map(part),
+                }],
+                machine: None,
+                single_threaded: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn round_trips_entries() {
+        let original = entry("abc1234", "box", 1.5, None);
+        let line = JsonValue::from(&original).stringify().unwrap();
+        assert!(!line.contains('\n'));
+
+        let parsed = HistoryEntry::try_from(line.as_str()).unwrap();
+        assert_eq!(parsed.commit, original.commit);
+        assert_eq!(parsed.timestamp, original.timestamp);
+        assert_eq!(parsed.machine, "box");
+        assert_eq!(parsed.timings.data[0].part_1, Some(part(1.5)));
+        assert_eq!(parsed.label(), "abc1234 on 2024-12-25");
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        assert!(HistoryEntry::try_from("{}").is_err());
+        assert!(HistoryEntry::try_from("not json").is_err());
+    }
+
+    #[test]
+    fn finds_latest_baseline_on_same_machine() {
+        let history = TimingHistory {
+            entries: vec![
+                entry("old", "box", 2.0, Some(5.0)),
+                entry("new", "box", 1.0, None),
+                entry("other", "laptop", 9.0, Some(9.0)),
+            ],
+        };
+
+        let (baseline, nanos) = history.baseline("box", day!(1), 1).unwrap();
+        assert_eq!(baseline.commit.as_deref(), Some("new"));
+        assert_eq!(nanos, 1_000_000.0);
+
+        // Part 2 is missing from the newest run, so it falls back to an older one
+        let (baseline, _) = history.baseline("box", day!(1), 2).unwrap();
+        assert_eq!(baseline.commit.as_deref(), Some("old"));
+
+        assert!(history.baseline("box", day!(2), 1).is_none());
+        assert!(history.baseline("server", day!(1), 1).is_none());
+    }
+
+    #[test]
+    fn flags_regressions_beyond_threshold() {
+        let history = TimingHistory {
+            entries: vec![entry("base", "box", 1.0, Some(10.0))],
+        };
+        let current = entry("head", "box", 1.2, Some(10.5)).timings;
+
+        let comparisons = history.compare("box", &current);
+        assert_eq!(comparisons.len(), 2);
+        assert!((comparisons[0].change() - 0.2).abs() < 1e-9);
+        assert!(comparisons[0].is_regression(0.1));
+        assert!(!comparisons[1].is_regression(0.1));
+        assert!(comparisons[1].is_regression(0.01));
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_735_084_800 + 86_399), "2024-12-25");
+    }
+}
@@ -0,0 +1,243 @@
+/// Describes the machine and toolchain a benchmark run was taken with, so stored numbers can be
+/// traced back to where they came from.
+use std::{collections::HashMap, env, fmt, fs, process::Command, thread};
+use tinyjson::JsonValue;
+
+/// Target features worth knowing about when comparing runs, checked when the template binary is
+/// compiled. Solutions are usually built with the same flags, but this cannot see their build.
+const TARGET_FEATURES: [(&str, bool); 12] = [
+    ("sse4.2", cfg!(target_feature = "sse4.2")),
+    ("popcnt", cfg!(target_feature = "popcnt")),
+    ("avx", cfg!(target_feature = "avx")),
+    ("avx2", cfg!(target_feature = "avx2")),
+    ("avx512f", cfg!(target_feature = "avx512f")),
+    ("bmi2", cfg!(target_feature = "bmi2")),
+    ("fma", cfg!(target_feature = "fma")),
+    ("neon", cfg!(target_feature = "neon")),
+    ("aes", cfg!(target_feature = "aes")),
+    ("sha2", cfg!(target_feature = "sha2")),
+    ("sve", cfg!(target_feature = "sve")),
+    ("lse", cfg!(target_feature = "lse")),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MachineInfo {
+    pub cpu: String,
+    /// Logical cores available to the process.
+    pub cores: usize,
+    /// Operating system and architecture, e.g. `linux x86_64`.
+    pub os: String,
+    pub kernel: String,
+    pub rustc: String,
+    /// Cargo profile the solutions were built with.
+    pub profile: String,
+    /// Target features the template binary was built with.
+    pub target_features: Vec<String>,
+    /// Size of the rayon pool the solutions were run with.
+    pub rayon_threads: usize,
+}
+
+impl MachineInfo {
+    /// Describe this machine, for solutions built in release mode or not and run with `threads`
+    /// rayon threads, or rayon's default pool size if not set.
+    pub fn capture(is_release: bool, threads: Option<usize>) -> Self {
+        Self {
+            cpu: cpu_model().unwrap_or_else(|| "unknown CPU".into()),
+            cores: thread::available_parallelism().map_or(1, usize::from),
+            os: format!("{} {}", env::consts::OS, env::consts::ARCH),
+            kernel: kernel_release().unwrap_or_else(|| "unknown".into()),
+            rustc: command_output(
+                &env::var("RUSTC").unwrap_or_else(|_| "rustc".into()),
+                &["--version"],
+            )
+            .unwrap_or_else(|| "unknown rustc".into()),
+            profile: if is_release { "release" } else { "dev" }.into(),
+            target_features: TARGET_FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            // the solutions size their pool like this process does, unless told otherwise.
+            rayon_threads: threads.unwrap_or_else(rayon::current_num_threads),
+        }
+    }
+}
+
+impl fmt::Display for MachineInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} cores), {} {}, {}, `{}` profile, {} rayon threads",
+            self.cpu,
+            self.cores,
+            self.os,
+            self.kernel,
+            self.rustc,
+            self.profile,
+            self.rayon_threads
+        )?;
+        if !self.target_features.is_empty() {
+            write!(
+                f,
+                ", template built with {}",
+                self.target_features.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn cpu_model() -> Option<String> {
+    let from_cpuinfo = fs::read_to_string("/proc/cpuinfo").ok().and_then(|info| {
+        info.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            matches!(key.trim(), "model name" | "Model" | "Hardware")
+                .then(|| value.trim().to_string())
+        })
+    });
+
+    from_cpuinfo
+        .or_else(|| command_output("sysctl", &["-n", "machdep.cpu.brand_string"]))
+        .or_else(|| env::var("PROCESSOR_IDENTIFIER").ok())
+}
+
+fn kernel_release() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/osrelease")
+        .ok()
+        .map(|release| release.trim().to_string())
+        .or_else(|| command_output("uname", &["-r"]))
+}
+
+/// Trimmed stdout of a successful command, or `None` if it failed or printed nothing.
+pub(crate) fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let s = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!s.is_empty()).then_some(s)
+}
+
+/* -------------------------------------------------------------------------- */
+
+impl From<&MachineInfo> for JsonValue {
+    fn from(value: &MachineInfo) -> Self {
+        let mut map: HashMap<String, JsonValue> = HashMap::new();
+
+        map.insert("cpu".into(), JsonValue::String(value.cpu.clone()));
+        map.insert("cores".into(), JsonValue::Number(value.cores as f64));
+        map.insert("os".into(), JsonValue::String(value.os.clone()));
+        map.insert("kernel".into(), JsonValue::String(value.kernel.clone()));
+        map.insert("rustc".into(), JsonValue::String(value.rustc.clone()));
+        map.insert("profile".into(), JsonValue::String(value.profile.clone()));
+        map.insert(
+            "target_features".into(),
+            JsonValue::Array(
+                value
+                    .target_features
+                    .iter()
+                    .cloned()
+                    .map(JsonValue::String)
+                    .collect(),
+            ),
+        );
+        map.insert(
+            "rayon_threads".into(),
+            JsonValue::Number(value.rayon_threads as f64),
+        );
+
+        JsonValue::Object(map)
+    }
+}
+
+impl TryFrom<&JsonValue> for MachineInfo {
+    type Error = String;
+
+    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+        let json = value
+            .get::<HashMap<String, JsonValue>>()
+            .ok_or("Expected machine to be a JSON object.")?;
+
+        let string = |key: &str| {
+            json.get(key)
+                .and_then(|v| v.get::<String>())
+                .cloned()
+                .ok_or(format!("Expected machine.{key} to be a string."))
+        };
+        let number = |key: &str| {
+            json.get(key)
+                .and_then(|v| v.get::<f64>())
+                .map(|&n| n as usize)
+                .ok_or(format!("Expected machine.{key} to be a number."))
+        };
+
+        let target_features = json
+            .get("target_features")
+            .and_then(|v| v.get::<Vec<JsonValue>>())
+            .and_then(|features| {
+                features
+                    .iter()
+                    .map(|feature| feature.get::<String>().cloned())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or("Expected machine.target_features to be an array of strings.")?;
+
+        Ok(MachineInfo {
+            cpu: string("cpu")?,
+            cores: number("cores")?,
+            os: string("os")?,
+            kernel: string("kernel")?,
+            rustc: string("rustc")?,
+            profile: string("profile")?,
+            target_features,
+            rayon_threads: number("rayon_threads")?,
+        })
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::MachineInfo;
+    use tinyjson::JsonValue;
+
+    fn get_mock_machine() -> MachineInfo {
+        MachineInfo {
+            cpu: "Apple M1 Pro".into(),
+            cores: 10,
+            os: "macos aarch64".into(),
+            kernel: "23.1.0".into(),
+            rustc: "rustc 1.83.0 (90b35a623 2024-11-26)".into(),
+            profile: "release".into(),
+            target_features: vec!["neon".into(), "aes".into()],
+            rayon_threads: 10,
+        }
+    }
+
+    #[test]
+    fn round_trips_machine_info() {
+        let machine = get_mock_machine();
+        let json = JsonValue::from(&machine);
+        assert_eq!(MachineInfo::try_from(&json).unwrap(), machine);
+        assert!(MachineInfo::try_from(&JsonValue::Null).is_err());
+    }
+
+    #[test]
+    fn describes_machine() {
+        assert_eq!(
+            get_mock_machine().to_string(),
+            "Apple M1 Pro (10 cores), macos aarch64 23.1.0, rustc 1.83.0 (90b35a623 2024-11-26), \
+            `release` profile, 10 rayon threads, template built with neon aes"
+        );
+    }
+
+    #[test]
+    fn captures_current_machine() {
+        let machine = MachineInfo::capture(true, None);
+        assert!(machine.cores >= 1);
+        assert!(machine.rayon_threads >= 1);
+        assert_eq!(machine.profile, "release");
+        assert_eq!(MachineInfo::capture(false, Some(3)).rayon_threads, 3);
+    }
+}
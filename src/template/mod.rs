@@ -1,13 +1,18 @@
 use std::{env, fs};
 
 pub mod aoc_cli;
+pub mod bench;
 pub mod commands;
 pub mod runner;
 
 pub use day::*;
 
+mod allocations;
 mod day;
-mod readme_benchmarks;
+mod export;
+mod history;
+mod machine;
+mod report;
 mod run_multi;
 mod timings;
 
@@ -39,19 +44,30 @@ pub fn read_file_part(folder: &str, day: Day, part: u8) -> String {
 /// Creates the constant `DAY` and sets up the input and runner for each part.
 ///
 /// The optional, second parameter (1 or 2) allows you to only run a single part of the solution.
+/// A trailing `parallel` marks a solution that parallelizes with rayon, so that
+/// `cargo time --single-threaded` also times it on one thread.
 #[macro_export]
 macro_rules! solution {
     ($day:expr) => {
-        $crate::solution!(@impl $day, [part_one, 1] [part_two, 2]);
+        $crate::solution!(@impl $day, false, [part_one, 1] [part_two, 2]);
     };
     ($day:expr, 1) => {
-        $crate::solution!(@impl $day, [part_one, 1]);
+        $crate::solution!(@impl $day, false, [part_one, 1]);
     };
     ($day:expr, 2) => {
-        $crate::solution!(@impl $day, [part_two, 2]);
+        $crate::solution!(@impl $day, false, [part_two, 2]);
+    };
+    ($day:expr, parallel) => {
+        $crate::solution!(@impl $day, true, [part_one, 1] [part_two, 2]);
+    };
+    ($day:expr, 1, parallel) => {
+        $crate::solution!(@impl $day, true, [part_one, 1]);
+    };
+    ($day:expr, 2, parallel) => {
+        $crate::solution!(@impl $day, true, [part_two, 2]);
     };
 
-    (@impl $day:expr, $( [$func:expr, $part:expr] )*) => {
+    (@impl $day:expr, $parallel:expr, $( [$func:expr, $part:expr] )*) => {
         /// The current day.
         const DAY: $crate::template::Day = $crate::day!($day);
 
@@ -61,8 +77,12 @@ macro_rules! solution {
 
         fn main() {
             use $crate::template::runner::*;
+            if $parallel {
+                print_parallel();
+            }
             let input = $crate::template::read_file("inputs", DAY);
             $( run_part($func, &input, DAY, $part); )*
+            exit_if_failed();
         }
     };
 }
@@ -0,0 +1,923 @@
+/// Module that fills sections of the readme with tables built from local data.
+/// The approach taken is similar to how `aoc-readme-stars` handles this.
+///
+/// Each section sits between two markers such as `<!--- benchmarking table --->`. The opening
+/// marker may carry options, e.g. `<!--- benchmarking table columns="day part_1 total" unit="ms" --->`:
+///  - `columns`: the columns to render, in order.
+///  - `unit`: `auto`, a duration unit (`ns`, `µs`, `ms`, `s`) or a size unit (`B`, `KiB`, `MiB`).
+///  - `title`: the heading above the table.
+use std::{fmt, fs, io, path::Path, str::FromStr, time::Duration};
+
+use crate::template::allocations::Allocations;
+use crate::template::run_multi::get_path_for_bin;
+use crate::template::timings::Timings;
+use crate::template::{all_days, Day};
+
+static README_PATH: &str = "README.md";
+static FOOTER_PREFIX: &str = "*Benchmarks performed on";
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum Error {
+    Parser(String),
+    IO(io::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IO(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parser(message) => write!(f, "{message}"),
+            Error::IO(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// A kind of table the readme can contain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Section {
+    Stars,
+    Benchmarks,
+    Allocations,
+    LinesOfCode,
+}
+
+impl Section {
+    pub const ALL: [Section; 4] = [
+        Section::Stars,
+        Section::Benchmarks,
+        Section::Allocations,
+        Section::LinesOfCode,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Section::Stars => "advent_readme_stars",
+            Section::Benchmarks => "benchmarking",
+            Section::Allocations => "allocations",
+            Section::LinesOfCode => "lines of code",
+        }
+    }
+
+    fn marker(self) -> String {
+        format!("<!--- {} table --->", self.name())
+    }
+
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            Section::Stars => &["day", "part_1", "part_2"],
+            Section::Benchmarks => &[
+                "day", "part_1", "part_2", "total", "samples", "spread", "single_1", "single_2",
+            ],
+            Section::Allocations => &[
+                "day", "part_1", "part_2", "peak_1", "peak_2", "blocks_1", "blocks_2",
+            ],
+            Section::LinesOfCode => &["day", "code", "tests", "total"],
+        }
+    }
+
+    fn default_columns(self) -> &'static [&'static str] {
+        match self {
+            Section::LinesOfCode => &["day", "code", "tests"],
+            _ => &["day", "part_1", "part_2"],
+        }
+    }
+
+    fn default_title(self, year: Option<&str>) -> String {
+        match self {
+            Section::Stars => match year {
+                Some(year) => format!("{year} Results"),
+                None => "Results".into(),
+            },
+            Section::Benchmarks => "Benchmarks".into(),
+            Section::Allocations => "Allocations".into(),
+            Section::LinesOfCode => "Lines of Code".into(),
+        }
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} table", self.name())
+    }
+}
+
+/// Unit to render durations or sizes in. `Auto` picks one per value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Auto,
+    Nanos,
+    Micros,
+    Millis,
+    Seconds,
+    Bytes,
+    KiB,
+    MiB,
+}
+
+impl Unit {
+    fn is_duration(self) -> bool {
+        matches!(
+            self,
+            Unit::Auto | Unit::Nanos | Unit::Micros | Unit::Millis | Unit::Seconds
+        )
+    }
+
+    fn is_size(self) -> bool {
+        matches!(self, Unit::Auto | Unit::Bytes | Unit::KiB | Unit::MiB)
+    }
+}
+
+impl FromStr for Unit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "auto" => Unit::Auto,
+            "ns" => Unit::Nanos,
+            "µs" | "us" => Unit::Micros,
+            "ms" => Unit::Millis,
+            "s" => Unit::Seconds,
+            "B" => Unit::Bytes,
+            "KiB" => Unit::KiB,
+            "MiB" => Unit::MiB,
+            _ => return Err(Error::Parser(format!("unknown unit `{s}`."))),
+        })
+    }
+}
+
+fn format_nanos(nanos: f64, unit: Unit) -> String {
+    match unit {
+        Unit::Nanos => format!("{nanos:.0}ns"),
+        Unit::Micros => format!("{:.1}µs", nanos / 1_000_f64),
+        Unit::Millis => format!("{:.2}ms", nanos / 1_000_000_f64),
+        Unit::Seconds => format!("{:.3}s", nanos / 1_000_000_000_f64),
+        _ => format!("{:.1?}", Duration::from_nanos(nanos as u64)),
+    }
+}
+
+fn format_bytes(bytes: u64, unit: Unit) -> String {
+    let unit = match unit {
+        Unit::Auto if bytes >= 1 << 20 => Unit::MiB,
+        Unit::Auto if bytes >= 1 << 10 => Unit::KiB,
+        Unit::Auto => Unit::Bytes,
+        unit => unit,
+    };
+
+    match unit {
+        Unit::KiB => format!("{:.1} KiB", bytes as f64 / f64::from(1 << 10)),
+        Unit::MiB => format!("{:.1} MiB", bytes as f64 / f64::from(1 << 20)),
+        _ => format!("{bytes} B"),
+    }
+}
+
+/// Lines in a solution, split at its test module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineCount {
+    pub day: Day,
+    pub code: usize,
+    pub tests: usize,
+}
+
+/// Everything the readme tables are built from.
+#[derive(Clone, Debug, Default)]
+pub struct ReportData {
+    pub year: Option<String>,
+    /// Stars earned per day, from 0 to 2, for the days whose puzzle has been downloaded.
+    pub stars: Vec<(Day, u8)>,
+    pub timings: Timings,
+    pub allocations: Allocations,
+    pub lines: Vec<LineCount>,
+}
+
+impl ReportData {
+    fn has_data(&self, section: Section) -> bool {
+        match section {
+            Section::Stars => !self.stars.is_empty(),
+            Section::Benchmarks => !self.timings.data.is_empty(),
+            Section::Allocations => !self.allocations.data.is_empty(),
+            Section::LinesOfCode => true,
+        }
+    }
+
+    /// Gathers stars from the downloaded puzzle descriptions, allocation statistics from the last
+    /// `solve --dhat` runs and line counts from the solution sources.
+    pub fn collect(timings: Timings) -> Self {
+        let stars = all_days()
+            .filter_map(|day| {
+                let puzzle = fs::read_to_string(format!("data/puzzles/{day}.md")).ok()?;
+                Some((day, count_stars(&puzzle)))
+            })
+            .collect();
+
+        let lines = all_days()
+            .filter_map(|day| {
+                let source = fs::read_to_string(get_path_for_bin(day)).ok()?;
+                let (code, tests) = count_lines(&source);
+                Some(LineCount { day, code, tests })
+            })
+            .collect();
+
+        Self {
+            year: std::env::var("AOC_YEAR").ok(),
+            stars,
+            timings,
+            allocations: Allocations::read_from_file(),
+            lines,
+        }
+    }
+}
+
+/// Stars earned for a puzzle, judging by the answers shown in its downloaded description.
+fn count_stars(puzzle: &str) -> u8 {
+    if puzzle.contains("Both parts of this puzzle are complete") {
+        2
+    } else {
+        puzzle.matches("Your puzzle answer was").count().min(2) as u8
+    }
+}
+
+/// Counts non-blank, non-comment lines before and after the `#[cfg(test)]` module.
+fn count_lines(source: &str) -> (usize, usize) {
+    let mut counts = (0, 0);
+    let mut in_tests = false;
+
+    for line in source.lines().map(str::trim) {
+        in_tests |= line.starts_with("#[cfg(test)]");
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if in_tests {
+            counts.1 += 1;
+        } else {
+            counts.0 += 1;
+        }
+    }
+
+    counts
+}
+
+/* -------------------------------------------------------------------------- */
+
+struct SectionPosition {
+    pos_start: usize,
+    pos_end: usize,
+    /// The opening marker as written, including its options.
+    opening: String,
+}
+
+fn locate_section(readme: &str, section: Section) -> Result<Option<SectionPosition>, Error> {
+    let prefix = format!("<!--- {} table", section.name());
+
+    let markers = readme
+        .match_indices(&prefix)
+        .map(|(start, _)| {
+            let len = readme[start..].find("--->").ok_or_else(|| {
+                Error::Parser(format!("{section}: unterminated marker in README."))
+            })?;
+            Ok((start, start + len + "--->".len()))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    match markers.as_slice() {
+        [] => Ok(None),
+        [(start, opening_end), (_, end)] => Ok(Some(SectionPosition {
+            pos_start: *start,
+            pos_end: *end,
+            opening: readme[*start..*opening_end].to_string(),
+        })),
+        [_] => Err(Error::Parser(format!(
+            "{section}: missing closing marker in README."
+        ))),
+        _ => Err(Error::Parser(format!(
+            "{section}: too many occurrences of marker in README."
+        ))),
+    }
+}
+
+struct SectionOptions {
+    columns: Vec<String>,
+    unit: Unit,
+    title: String,
+}
+
+/// Reads the `key="value"` options of an opening marker.
+fn parse_options(
+    section: Section,
+    opening: &str,
+    year: Option<&str>,
+) -> Result<SectionOptions, Error> {
+    let mut options = SectionOptions {
+        columns: section
+            .default_columns()
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        unit: Unit::Auto,
+        title: section.default_title(year),
+    };
+
+    let mut rest = opening
+        .trim_start_matches(&format!("<!--- {} table", section.name()))
+        .trim_end_matches("--->")
+        .trim();
+
+    while !rest.is_empty() {
+        let (key, after_key) = rest.split_once('=').ok_or_else(|| {
+            Error::Parser(format!("{section}: expected `key=value`, found `{rest}`."))
+        })?;
+
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').ok_or_else(|| {
+                Error::Parser(format!("{section}: unterminated quote in option `{key}`."))
+            })?,
+            None => after_key.split_once(' ').unwrap_or((after_key, "")),
+        };
+
+        match key.trim() {
+            "columns" => {
+                options.columns = value.split_whitespace().map(ToString::to_string).collect();
+                if let Some(column) = options
+                    .columns
+                    .iter()
+                    .find(|column| !section.columns().contains(&column.as_str()))
+                {
+                    return Err(Error::Parser(format!(
+                        "{section}: unknown column `{column}`, expected one of {}.",
+                        section.columns().join(", ")
+                    )));
+                }
+            }
+            "unit" => {
+                let unit = Unit::from_str(value)?;
+                let applies = match section {
+                    Section::Benchmarks => unit.is_duration(),
+                    Section::Allocations => unit.is_size(),
+                    _ => unit == Unit::Auto,
+                };
+                if !applies {
+                    return Err(Error::Parser(format!(
+                        "{section}: unit `{value}` does not apply to this table."
+                    )));
+                }
+                options.unit = unit;
+            }
+            "title" => value.clone_into(&mut options.title),
+            key => return Err(Error::Parser(format!("{section}: unknown option `{key}`."))),
+        }
+
+        rest = after_value.trim_start();
+    }
+
+    Ok(options)
+}
+
+fn header(column: &str) -> String {
+    match column {
+        "day" => "Day".into(),
+        "code" => "Code".into(),
+        "tests" => "Tests".into(),
+        "total" => "Total".into(),
+        "samples" => "Samples".into(),
+        "spread" => "Spread".into(),
+        column => {
+            let (name, part) = column.rsplit_once('_').unwrap_or((column, ""));
+            let mut name = name.to_string();
+            name[..1].make_ascii_uppercase();
+            format!("{name} {part}")
+        }
+    }
+}
+
+fn source_link(day: Day) -> String {
+    format!("[Day {}]({})", day.into_inner(), get_path_for_bin(day))
+}
+
+fn code(s: impl fmt::Display) -> String {
+    format!("`{s}`")
+}
+
+/// The cells of every row, followed by an optional summary line below the table.
+fn section_rows(
+    section: Section,
+    options: &SectionOptions,
+    data: &ReportData,
+) -> (Vec<Vec<String>>, Option<String>) {
+    let unit = options.unit;
+    let columns = &options.columns;
+    let missing = || code("-");
+
+    match section {
+        Section::Stars => {
+            let rows = data
+                .stars
+                .iter()
+                .map(|&(day, stars)| {
+                    columns
+                        .iter()
+                        .map(|column| match column.as_str() {
+                            "day" => match &data.year {
+                                Some(year) => format!(
+                                    "[Day {}](https://adventofcode.com/{year}/day/{})",
+                                    day.into_inner(),
+                                    day.into_inner()
+                                ),
+                                None => format!("Day {}", day.into_inner()),
+                            },
+                            "part_1" if stars >= 1 => "⭐".into(),
+                            "part_2" if stars >= 2 => "⭐".into(),
+                            _ => String::new(),
+                        })
+                        .collect()
+                })
+                .collect();
+            (rows, None)
+        }
+        Section::Benchmarks => {
+            let part =
+                |part: Option<f64>| part.map_or_else(missing, |n| code(format_nanos(n, unit)));
+            let rows = data
+                .timings
+                .data
+                .iter()
+                .map(|timing| {
+                    columns
+                        .iter()
+                        .map(|column| match column.as_str() {
+                            "day" => source_link(timing.day),
+                            "part_1" => part(timing.part_1.map(|p| p.nanos)),
+                            "part_2" => part(timing.part_2.map(|p| p.nanos)),
+                            "total" => part(Some(timing.total_nanos())),
+                            "single_1" | "single_2" => {
+                                let single = data.timings.single_threaded(timing.day);
+                                let part_number = if column == "single_1" { 1 } else { 2 };
+                                part(single.and_then(|t| t.part(part_number)).map(|p| p.nanos))
+                            }
+                            "samples" => [timing.part_1, timing.part_2]
+                                .map(|p| p.map_or_else(missing, |p| code(p.samples)))
+                                .join(" / "),
+                            _ => [timing.part_1, timing.part_2]
+                                .map(|p| {
+                                    p.map_or_else(missing, |p| {
+                                        code(format!("±{}", format_nanos(p.spread_nanos, unit)))
+                                    })
+                                })
+                                .join(" / "),
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let total = match unit {
+                Unit::Auto => format!("{:.2}ms", data.timings.total_millis()),
+                unit => format_nanos(data.timings.total_millis() * 1_000_000_f64, unit),
+            };
+            (rows, Some(format!("**Total: {total}**")))
+        }
+        Section::Allocations => {
+            let rows = data
+                .allocations
+                .data
+                .iter()
+                .map(|allocations| {
+                    columns
+                        .iter()
+                        .map(|column| {
+                            if column == "day" {
+                                return source_link(allocations.day);
+                            }
+                            let part = if column.ends_with('1') { 1 } else { 2 };
+                            allocations.part(part).map_or_else(missing, |stats| {
+                                if column.starts_with("peak") {
+                                    code(format_bytes(stats.peak_bytes, unit))
+                                } else if column.starts_with("blocks") {
+                                    code(stats.total_blocks)
+                                } else {
+                                    code(format_bytes(stats.total_bytes, unit))
+                                }
+                            })
+                        })
+                        .collect()
+                })
+                .collect();
+            (rows, None)
+        }
+        Section::LinesOfCode => {
+            let rows = data
+                .lines
+                .iter()
+                .map(|lines| {
+                    columns
+                        .iter()
+                        .map(|column| match column.as_str() {
+                            "day" => source_link(lines.day),
+                            "code" => lines.code.to_string(),
+                            "tests" => lines.tests.to_string(),
+                            _ => (lines.code + lines.tests).to_string(),
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let total: usize = data.lines.iter().map(|lines| lines.code).sum();
+            (rows, Some(format!("**Total: {total} lines of code**")))
+        }
+    }
+}
+
+/// Builds a section. `previous` is its current content, from which a hand-written benchmark
+/// footer is kept when no machine was recorded with the timings.
+fn construct_table(
+    section: Section,
+    opening: &str,
+    previous: &str,
+    data: &ReportData,
+) -> Result<String, Error> {
+    let options = parse_options(section, opening, data.year.as_deref())?;
+    let (rows, summary) = section_rows(section, &options, data);
+
+    let row = |cells: &[String]| format!("| {} |", cells.join(" | "));
+    let headers: Vec<String> = options.columns.iter().map(|c| header(c)).collect();
+    let alignment = vec![":---:".to_string(); options.columns.len()];
+
+    let mut lines: Vec<String> = vec![
+        opening.into(),
+        format!("## {}", options.title),
+        String::new(),
+        row(&headers),
+        row(&alignment),
+    ];
+    lines.extend(rows.iter().map(|cells| row(cells)));
+
+    if let Some(summary) = summary {
+        lines.push(String::new());
+        lines.push(summary);
+    }
+
+    if section == Section::Benchmarks {
+        let footer = match &data.timings.machine {
+            Some(machine) => Some(format!("{FOOTER_PREFIX} {machine}*")),
+            None => previous
+                .lines()
+                .find(|line| line.starts_with(FOOTER_PREFIX))
+                .map(ToString::to_string),
+        };
+        if let Some(footer) = footer {
+            lines.push(String::new());
+            lines.push(footer);
+        }
+    }
+    lines.push(section.marker());
+
+    Ok(lines.join("\n"))
+}
+
+/// Regenerates one section in place. Returns whether its content changed.
+fn update_section(s: &mut String, section: Section, data: &ReportData) -> Result<bool, Error> {
+    let position = locate_section(s, section)?
+        .ok_or_else(|| Error::Parser(format!("{section}: could not find marker in README.")))?;
+
+    let previous = &s[position.pos_start..position.pos_end];
+    let table = construct_table(section, &position.opening, previous, data)?;
+    if s[position.pos_start..position.pos_end] == table {
+        return Ok(false);
+    }
+
+    s.replace_range(position.pos_start..position.pos_end, &table);
+    Ok(true)
+}
+
+/// Regenerates every section that has markers in the readme. Returns the sections that changed.
+/// Sections without any local data are left alone, as puzzle descriptions, timings and
+/// allocations are not committed and a fresh checkout would otherwise wipe their tables.
+fn update_content(s: &mut String, data: &ReportData) -> Result<Vec<Section>, Error> {
+    let mut changed = vec![];
+
+    for section in Section::ALL {
+        if !data.has_data(section) {
+            continue;
+        }
+        if locate_section(s, section)?.is_some() && update_section(s, section, data)? {
+            changed.push(section);
+        }
+    }
+
+    Ok(changed)
+}
+
+fn read_readme() -> Result<String, Error> {
+    Ok(String::from_utf8_lossy(&fs::read(Path::new(README_PATH))?).to_string())
+}
+
+/// Rewrites the readme with fresh tables. Returns the sections that changed.
+pub fn update(data: &ReportData) -> Result<Vec<Section>, Error> {
+    let mut readme = read_readme()?;
+    let changed = update_content(&mut readme, data)?;
+    if !changed.is_empty() {
+        fs::write(README_PATH, &readme)?;
+    }
+    Ok(changed)
+}
+
+/// Returns the sections of the readme that are out of date, without touching the file.
+pub fn check(data: &ReportData) -> Result<Vec<Section>, Error> {
+    let mut readme = read_readme()?;
+    update_content(&mut readme, data)
+}
+
+/* -------------------------------------------------------------------------- */
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{
+        count_lines, count_stars, update_content, update_section, LineCount, ReportData, Section,
+    };
+    use crate::{
+        day,
+        template::allocations::{AllocationStats, Allocations, DayAllocations},
+        template::machine::MachineInfo,
+        template::timings::{PartTiming, Timing, Timings},
+    };
+
+    static MARKER: &str = "<!--- benchmarking table --->";
+
+    fn part(millis: f64) -> PartTiming {
+        PartTiming {
+            nanos: millis * 1_000_000_f64,
+            samples: 100,
+            spread_nanos: 250_000_f64,
+        }
+    }
+
+    fn get_mock_data() -> ReportData {
+        ReportData {
+            year: Some("2024".into()),
+            stars: vec![(day!(1), 2), (day!(2), 1), (day!(3), 0)],
+            timings: Timings {
+                data: vec![
+                    Timing {
+                        day: day!(1),
+                        part_1: Some(part(10.0)),
+                        part_2: Some(part(20.0)),
+                    },
+                    Timing {
+                        day: day!(2),
+                        part_1: Some(part(30.0)),
+                        part_2: Some(part(40.0)),
+                    },
+                    Timing {
+                        day: day!(4),
+                        part_1: Some(part(40.0)),
+                        part_2: Some(part(50.0)),
+                    },
+                ],
+                machine: None,
+                single_threaded: vec![],
+            },
+            allocations: Allocations {
+                data: vec![DayAllocations {
+                    day: day!(1),
+                    part_1: Some(AllocationStats {
+                        total_bytes: 3 << 20,
+                        total_blocks: 12,
+                        peak_bytes: 1536,
+                    }),
+                    part_2: None,
+                }],
+            },
+            lines: vec![
+                LineCount {
+                    day: day!(1),
+                    code: 40,
+                    tests: 12,
+                },
+                LineCount {
+                    day: day!(2),
+                    code: 60,
+                    tests: 0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn errors_if_marker_not_present() {
+        let mut s = "# readme".to_string();
+        update_section(&mut s, Section::Benchmarks, &get_mock_data()).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn errors_if_too_many_markers_present() {
+        let mut s = format!("{} {} {}", MARKER, MARKER, MARKER);
+        update_content(&mut s, &get_mock_data()).unwrap();
+    }
+
+    #[test]
+    fn updates_empty_benchmarks() {
+        let mut s = format!("foo\nbar\n{}{}\nbaz", MARKER, MARKER);
+        update_content(&mut s, &get_mock_data()).unwrap();
+        assert_eq!(s.contains("## Benchmarks"), true);
+    }
+
+    #[test]
+    fn updates_existing_benchmarks() {
+        let mut s = format!("foo\nbar\n{}{}\nbaz", MARKER, MARKER);
+        let changed = update_content(&mut s, &get_mock_data()).unwrap();
+        assert_eq!(changed, vec![Section::Benchmarks]);
+
+        // A second pass finds nothing out of date
+        let changed = update_content(&mut s, &get_mock_data()).unwrap();
+        assert!(changed.is_empty());
+        assert_eq!(s.matches(MARKER).collect::<Vec<&str>>().len(), 2);
+        assert_eq!(s.matches("## Benchmarks").collect::<Vec<&str>>().len(), 1);
+    }
+
+    #[test]
+    fn format_benchmarks() {
+        let mut s = format!("foo\nbar\n{}\n{}\nbaz", MARKER, MARKER);
+        update_content(&mut s, &get_mock_data()).unwrap();
+        let expected = [
+            "foo",
+            "bar",
+            "<!--- benchmarking table --->",
+            "## Benchmarks",
+            "",
+            "| Day | Part 1 | Part 2 |",
+            "| :---: | :---: | :---: |",
+            "| [Day 1](./src/bin/01.rs) | `10.0ms` | `20.0ms` |",
+            "| [Day 2](./src/bin/02.rs) | `30.0ms` | `40.0ms` |",
+            "| [Day 4](./src/bin/04.rs) | `40.0ms` | `50.0ms` |",
+            "",
+            "**Total: 190.00ms**",
+            "<!--- benchmarking table --->",
+            "baz",
+        ]
+        .join("\n");
+        assert_eq!(s, expected);
+    }
+
+    #[test]
+    fn keeps_options_and_applies_columns_and_units() {
+        let opening =
+            r#"<!--- benchmarking table columns="day total spread" unit="µs" title="Speed" --->"#;
+        let mut s = format!("{opening}\n{MARKER}");
+        update_content(&mut s, &get_mock_data()).unwrap();
+
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!(lines[0], opening);
+        assert_eq!(lines[1], "## Speed");
+        assert_eq!(lines[3], "| Day | Total | Spread |");
+        assert_eq!(
+            lines[5],
+            "| [Day 1](./src/bin/01.rs) | `30000.0µs` | `±250.0µs` / `±250.0µs` |"
+        );
+        assert_eq!(lines[9], "**Total: 190000.0µs**");
+    }
+
+    #[test]
+    fn renders_machine_footer() {
+        let mut data = get_mock_data();
+        data.timings.machine = Some(MachineInfo {
+            cpu: "Apple M1 Pro".into(),
+            cores: 10,
+            os: "macos aarch64".into(),
+            kernel: "23.1.0".into(),
+            rustc: "rustc 1.83.0".into(),
+            profile: "release".into(),
+            target_features: vec![],
+            rayon_threads: 8,
+        });
+
+        let mut s = format!("{MARKER}\n{MARKER}");
+        update_content(&mut s, &data).unwrap();
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!(
+            lines[lines.len() - 2],
+            "*Benchmarks performed on Apple M1 Pro (10 cores), macos aarch64 23.1.0, rustc 1.83.0, \
+            `release` profile, 8 rayon threads*"
+        );
+        assert_eq!(lines[lines.len() - 4], "**Total: 190.00ms**");
+    }
+
+    #[test]
+    fn keeps_hand_written_footer_without_machine() {
+        let footer = "*Benchmarks performed on a 2021 M1 Macbook Pro, 16GB*";
+        let mut s = format!("{MARKER}\n\n{footer}\n{MARKER}");
+        update_content(&mut s, &get_mock_data()).unwrap();
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!(lines[lines.len() - 2], footer);
+        assert_eq!(lines[lines.len() - 4], "**Total: 190.00ms**");
+    }
+
+    #[test]
+    fn renders_single_threaded_columns() {
+        let mut data = get_mock_data();
+        data.timings.single_threaded = vec![Timing {
+            day: day!(2),
+            part_1: Some(part(60.0)),
+            part_2: None,
+        }];
+
+        let mut s = format!(
+            "{}\n{MARKER}",
+            r#"<!--- benchmarking table columns="day part_1 single_1 single_2" --->"#
+        );
+        update_content(&mut s, &data).unwrap();
+        assert!(s.contains("| Day | Part 1 | Single 1 | Single 2 |"));
+        assert!(s.contains("| [Day 1](./src/bin/01.rs) | `10.0ms` | `-` | `-` |"));
+        assert!(s.contains("| [Day 2](./src/bin/02.rs) | `30.0ms` | `60.0ms` | `-` |"));
+    }
+
+    #[test]
+    fn leaves_sections_without_data_alone() {
+        let readme = [
+            "<!--- advent_readme_stars table --->",
+            "stars",
+            "<!--- advent_readme_stars table --->",
+            MARKER,
+            "benchmarks",
+            MARKER,
+            "<!--- allocations table --->",
+            "allocations",
+            "<!--- allocations table --->",
+        ]
+        .join("\n");
+
+        let mut s = readme.clone();
+        let changed = update_content(&mut s, &ReportData::default()).unwrap();
+        assert!(changed.is_empty());
+        assert_eq!(s, readme);
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        for opening in [
+            r#"<!--- benchmarking table columns="day bogus" --->"#,
+            r#"<!--- benchmarking table unit="KiB" --->"#,
+            r#"<!--- benchmarking table colour=red --->"#,
+            r#"<!--- benchmarking table title="unterminated --->"#,
+        ] {
+            let mut s = format!("{opening}\n{MARKER}");
+            assert!(
+                update_content(&mut s, &get_mock_data()).is_err(),
+                "{opening}"
+            );
+        }
+    }
+
+    #[test]
+    fn fills_every_section() {
+        let mut s = [
+            "<!--- advent_readme_stars table --->",
+            "<!--- advent_readme_stars table --->",
+            r#"<!--- allocations table columns="day part_1 peak_1 blocks_1 part_2" --->"#,
+            "<!--- allocations table --->",
+            r#"<!--- lines of code table columns="day code tests total" --->"#,
+            "<!--- lines of code table --->",
+        ]
+        .join("\n");
+        let changed = update_content(&mut s, &get_mock_data()).unwrap();
+        assert_eq!(
+            changed,
+            vec![Section::Stars, Section::Allocations, Section::LinesOfCode]
+        );
+
+        assert!(s.contains("## 2024 Results"));
+        assert!(s.contains("| [Day 1](https://adventofcode.com/2024/day/1) | ⭐ | ⭐ |"));
+        assert!(s.contains("| [Day 2](https://adventofcode.com/2024/day/2) | ⭐ |  |"));
+        assert!(s.contains("| [Day 3](https://adventofcode.com/2024/day/3) |  |  |"));
+
+        assert!(s.contains("| Day | Part 1 | Peak 1 | Blocks 1 | Part 2 |"));
+        assert!(s.contains("| [Day 1](./src/bin/01.rs) | `3.0 MiB` | `1.5 KiB` | `12` | `-` |"));
+
+        assert!(s.contains("| [Day 1](./src/bin/01.rs) | 40 | 12 | 52 |"));
+        assert!(s.contains("**Total: 100 lines of code**"));
+    }
+
+    #[test]
+    fn counts_stars_from_puzzle_descriptions() {
+        assert_eq!(count_stars("--- Day 1 ---"), 0);
+        assert_eq!(count_stars("Your puzzle answer was `1`."), 1);
+        assert_eq!(
+            count_stars("Your puzzle answer was `1`.\nYour puzzle answer was `2`."),
+            2
+        );
+        assert_eq!(
+            count_stars("Your puzzle answer was `1`.\nBoth parts of this puzzle are complete!"),
+            2
+        );
+    }
+
+    #[test]
+    fn counts_lines_outside_and_inside_tests() {
+        let source = "use std::fmt;\n\n// comment\nfn main() {}\n\n#[cfg(test)]\nmod tests {\n    // note\n}\n";
+        assert_eq!(count_lines(source), (2, 3));
+    }
+}
@@ -1,5 +1,11 @@
-use std::{collections::HashSet, io};
-
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use std::{fmt, io, process, thread};
+
+use crate::template::bench::{format_duration, format_size, BenchFile, BenchOverrides, RunLimits};
+use crate::template::runner::PARALLEL_MARKER;
 use crate::template::{Day, ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
 
 use super::{
@@ -7,42 +13,215 @@ use super::{
     timings::{Timing, Timings},
 };
 
-pub fn run_multi(days_to_run: &HashSet<Day>, is_release: bool, is_timed: bool) -> Option<Timings> {
-    let mut timings: Vec<Timing> = Vec::with_capacity(days_to_run.len());
+/// How [`run_multi`] invokes the solutions.
+#[derive(Clone, Debug, Default)]
+pub struct RunOptions {
+    pub is_release: bool,
+    pub is_timed: bool,
+    /// Forwarded to every solution when timed. Its run limits apply to every run.
+    pub bench: BenchOverrides,
+    /// Size of the rayon pool in every solution, instead of one thread per core.
+    pub threads: Option<usize>,
+    /// When timed, also time the days that use rayon with a single thread.
+    pub single_threaded: bool,
+    /// Number of days to run at once. Timed runs always run one day at a time, as solutions
+    /// running side by side would skew each other's timings.
+    pub jobs: usize,
+}
 
-    let mut need_space = false;
+/// Why a day did not run to completion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Failure {
+    TimedOut(Duration),
+    /// Allocation failed, under the given memory limit if one was set.
+    OutOfMemory(Option<u64>),
+    /// The solution exited unsuccessfully outside of a part, with the given exit status.
+    Crashed(String),
+    /// A part panicked. Other parts of the day still ran.
+    Panicked {
+        part: u8,
+        location: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::TimedOut(timeout) => write!(f, "timed out after {timeout:?}"),
+            Failure::OutOfMemory(Some(limit)) => {
+                write!(f, "out of memory (limit {})", format_size(*limit))
+            }
+            Failure::OutOfMemory(None) => write!(f, "out of memory"),
+            Failure::Crashed(status) => write!(f, "crashed ({status})"),
+            Failure::Panicked {
+                part,
+                location,
+                message,
+            } => write!(f, "part {part} panicked at {location}: {message}"),
+        }
+    }
+}
+
+/// What [`run_multi`] ran into.
+#[derive(Clone, Debug, Default)]
+pub struct RunSummary {
+    /// Timings of the days that completed, when timed.
+    pub timings: Option<Timings>,
+    pub failures: Vec<(Day, Failure)>,
+}
 
+/// Runs the given days in order. When timed, the total budget of `options.bench` (or the one in
+/// `bench.json`) caps the wall-clock time spent; days that no longer fit are skipped.
+/// Days that exceed their run limits are stopped and reported in the summary.
+pub fn run_multi(days_to_run: &HashSet<Day>, options: &RunOptions) -> RunSummary {
     // NOTE: use non-duplicate, sorted day values.
-    all_days()
-        .filter(|day| days_to_run.contains(day))
-        .for_each(|day| {
-            if need_space {
-                println!();
+    let days: Vec<Day> = all_days().filter(|day| days_to_run.contains(day)).collect();
+    let bench_file = BenchFile::read_from_file();
+
+    // build up front, so that building does not count towards the timeout of a day.
+    if let Err(e) = child_commands::build_solutions(&days, options.is_release) {
+        eprintln!("Failed to build solutions: {e}.");
+        process::exit(1);
+    }
+
+    if options.jobs > 1 && !options.is_timed {
+        let failures = run_parallel(&days, &bench_file, options);
+        print_failures(&failures);
+        return RunSummary {
+            timings: None,
+            failures,
+        };
+    }
+
+    let mut timings: Vec<Timing> = Vec::with_capacity(days_to_run.len());
+    let mut single_threaded: Vec<Timing> = vec![];
+    let mut failures: Vec<(Day, Failure)> = vec![];
+
+    let total_budget = options
+        .bench
+        .total_budget
+        .or(bench_file.defaults.total_budget)
+        .filter(|_| options.is_timed);
+    let start = Instant::now();
+
+    // arguments for a solution binary, or `None` once the total budget is exhausted.
+    let child_args = |threads: Option<usize>, limits: &RunLimits| {
+        let mut args = limit_args(limits);
+        if options.is_timed {
+            args.push("--time".into());
+            args.extend(options.bench.to_args());
+            if let Some(total_budget) = total_budget {
+                let time_left = total_budget.checked_sub(start.elapsed())?;
+                args.extend(["--deadline".into(), format_duration(time_left)]);
             }
-            need_space = true;
+        }
+        if let Some(threads) = threads {
+            args.extend(["--threads".into(), threads.to_string()]);
+        }
+        Some(args)
+    };
 
-            println!("{ANSI_BOLD}Day {day}{ANSI_RESET}");
-            println!("------");
+    let mut need_space = false;
+
+    days.iter().for_each(|&day| {
+        if need_space {
+            println!();
+        }
+        need_space = true;
 
-            let output = child_commands::run_solution(day, is_timed, is_release).unwrap();
+        println!("{ANSI_BOLD}Day {day}{ANSI_RESET}");
+        println!("------");
 
-            if output.is_empty() {
-                println!("Not solved.");
-            } else {
-                let val = child_commands::parse_exec_time(&output, day);
-                timings.push(val);
+        let limits = RunLimits::resolve(&bench_file, day, &options.bench);
+        let Some(args) = child_args(options.threads, &limits) else {
+            println!("Skipped: total bench budget exhausted.");
+            return;
+        };
+        let output = child_commands::run_solution(day, options.is_release, &args, &limits).unwrap();
+
+        if record_failures(day, &output, &mut failures) {
+            return;
+        }
+        if output.stdout.is_empty() {
+            println!("Not solved.");
+            return;
+        }
+        timings.push(child_commands::parse_exec_time(&output.stdout, day));
+
+        if options.is_timed && options.single_threaded && is_parallel(&output.stdout) {
+            println!("{ANSI_ITALIC}Single-threaded{ANSI_RESET}");
+            let Some(args) = child_args(Some(1), &limits) else {
+                println!("Skipped: total bench budget exhausted.");
+                return;
+            };
+            let output =
+                child_commands::run_solution(day, options.is_release, &args, &limits).unwrap();
+            if !record_failures(day, &output, &mut failures) && !output.stdout.is_empty() {
+                single_threaded.push(child_commands::parse_exec_time(&output.stdout, day));
             }
-        });
+        }
+    });
+
+    print_failures(&failures);
 
-    if is_timed {
-        let timings = Timings { data: timings };
+    let timings = options.is_timed.then(|| {
+        let timings = Timings {
+            data: timings,
+            machine: None,
+            single_threaded,
+        };
         let total_millis = timings.total_millis();
         println!(
             "\n{ANSI_BOLD}Total (Run):{ANSI_RESET} {ANSI_ITALIC}{total_millis:.2}ms{ANSI_RESET}"
         );
-        Some(timings)
-    } else {
-        None
+        timings
+    });
+
+    RunSummary { timings, failures }
+}
+
+/// Arguments that make a solution binary enforce `limits` on itself.
+/// Timeouts are enforced by the parent instead, as a stuck solution cannot stop itself.
+fn limit_args(limits: &RunLimits) -> Vec<String> {
+    match limits.memory_limit {
+        Some(bytes) => vec!["--memory-limit".into(), bytes.to_string()],
+        None => vec![],
+    }
+}
+
+/// Add the failures of a run of `day` to `failures`, printing those the solution could not print
+/// itself. Returns whether the run was cut short, which leaves its output incomplete.
+fn record_failures(
+    day: Day,
+    output: &child_commands::SolutionOutput,
+    failures: &mut Vec<(Day, Failure)>,
+) -> bool {
+    let mut cut_short = false;
+
+    for failure in &output.failures {
+        if !matches!(failure, Failure::Panicked { .. }) {
+            println!("Failed: {failure}.");
+            cut_short = true;
+        }
+        // a single-threaded rerun can fail the same way.
+        let failure = (day, failure.clone());
+        if !failures.contains(&failure) {
+            failures.push(failure);
+        }
+    }
+
+    cut_short
+}
+
+fn print_failures(failures: &[(Day, Failure)]) {
+    if failures.is_empty() {
+        return;
+    }
+
+    println!("\n{ANSI_BOLD}Failed{ANSI_RESET}");
+    for (day, failure) in failures {
+        println!("Day {day}: {failure}");
     }
 }
 
@@ -51,6 +230,18 @@ pub fn run_multi(days_to_run: &HashSet<Day>, is_release: bool, is_timed: bool) -
 pub enum Error {
     BrokenPipe,
     IO(io::Error),
+    /// `cargo build` exited unsuccessfully, after printing its errors.
+    BuildFailed(process::ExitStatus),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::BrokenPipe => write!(f, "broken pipe"),
+            Error::IO(e) => write!(f, "{e}"),
+            Error::BuildFailed(status) => write!(f, "cargo build failed with {status}"),
+        }
+    }
 }
 
 impl From<std::io::Error> for Error {
@@ -64,68 +255,349 @@ pub fn get_path_for_bin(day: Day) -> String {
     format!("./src/bin/{day}.rs")
 }
 
+/// Runs days on up to `options.jobs` workers. The output of each day is buffered and printed
+/// once every day before it has been printed, so it reads the same as a sequential run.
+/// Expects the solutions to be built already.
+fn run_parallel(days: &[Day], bench_file: &BenchFile, options: &RunOptions) -> Vec<(Day, Failure)> {
+    let next_day = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut failures = vec![];
+
+    thread::scope(|scope| {
+        for _ in 0..options.jobs.min(days.len()) {
+            let sender = sender.clone();
+            let next_day = &next_day;
+
+            scope.spawn(move || {
+                while let Some(&day) = days.get(next_day.fetch_add(1, Ordering::Relaxed)) {
+                    let limits = RunLimits::resolve(bench_file, day, &options.bench);
+                    let mut child_args = limit_args(&limits);
+                    if let Some(threads) = options.threads {
+                        child_args.extend(["--threads".into(), threads.to_string()]);
+                    }
+
+                    let output = child_commands::run_solution_buffered(
+                        day,
+                        options.is_release,
+                        &child_args,
+                        &limits,
+                    );
+                    if sender.send((day, output)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut in_order = InOrder::new(days);
+        for (day, output) in receiver {
+            for (day, output) in in_order.push(day, output) {
+                let output = output.unwrap();
+
+                if day != days[0] {
+                    println!();
+                }
+                println!("{ANSI_BOLD}Day {day}{ANSI_RESET}");
+                println!("------");
+
+                output.stderr.iter().for_each(|line| eprintln!("{line}"));
+                output.stdout.iter().for_each(|line| println!("{line}"));
+                if !record_failures(day, &output, &mut failures) && output.stdout.is_empty() {
+                    println!("Not solved.");
+                }
+            }
+        }
+    });
+
+    failures
+}
+
+/// Holds back results that arrive out of order until all results before them are in.
+struct InOrder<T> {
+    order: Vec<Day>,
+    pending: HashMap<Day, T>,
+    next: usize,
+}
+
+impl<T> InOrder<T> {
+    fn new(order: &[Day]) -> Self {
+        Self {
+            order: order.to_vec(),
+            pending: HashMap::new(),
+            next: 0,
+        }
+    }
+
+    /// Adds the result for `day`, and returns every result that is now next in order.
+    fn push(&mut self, day: Day, item: T) -> Vec<(Day, T)> {
+        self.pending.insert(day, item);
+
+        let mut ready = vec![];
+        while let Some(item) = self
+            .order
+            .get(self.next)
+            .and_then(|day| self.pending.remove(day))
+        {
+            ready.push((self.order[self.next], item));
+            self.next += 1;
+        }
+        ready
+    }
+}
+
+/// Whether a timed solution announced that it parallelizes with rayon, so its timings depend on
+/// the thread count.
+fn is_parallel(stdout: &[String]) -> bool {
+    stdout.iter().any(|line| line.contains(PARALLEL_MARKER))
+}
+
 /// All solutions live in isolated binaries.
 /// This module encapsulates interaction with these binaries, both invoking them as well as parsing the timing output.
 pub mod child_commands {
-    use super::{get_path_for_bin, Error};
-    use crate::template::Day;
+    use super::{get_path_for_bin, Error, Failure};
+    use crate::template::{
+        bench::RunLimits,
+        timings::{parse_duration, PartTiming},
+        Day,
+    };
     use std::{
+        env,
         io::{BufRead, BufReader},
         path::Path,
-        process::{Command, Stdio},
+        process::{Child, Command, ExitStatus, Stdio},
         thread,
+        time::{Duration, Instant},
     };
 
-    /// Run the solution bin for a given day
-    pub fn run_solution(day: Day, is_timed: bool, is_release: bool) -> Result<Vec<String>, Error> {
+    /// How often a running solution is checked for having finished, when it has a timeout.
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Output of a solution bin, and what went wrong while running it.
+    #[derive(Clone, Debug, Default)]
+    pub struct SolutionOutput {
+        pub stdout: Vec<String>,
+        pub stderr: Vec<String>,
+        pub failures: Vec<Failure>,
+    }
+
+    /// Runs the solution binary built by [`build_solutions`] directly rather than through
+    /// `cargo run`, so that the process the timeout kills is the solution itself.
+    fn solution_command(day: Day, is_release: bool, child_args: &[String]) -> Command {
+        let target_dir = env::var_os("CARGO_TARGET_DIR").unwrap_or_else(|| "target".into());
+        let profile = if is_release { "release" } else { "debug" };
+        let bin = Path::new(&target_dir)
+            .join(profile)
+            .join(format!("{day}{}", env::consts::EXE_SUFFIX));
+
+        let mut cmd = Command::new(bin);
+        // mirror `--time` and related flags to child invocations.
+        cmd.args(child_args);
+        cmd
+    }
+
+    /// Build the solution bins of the given days up front, so that solutions running side by
+    /// side do not wait on each other's builds.
+    pub fn build_solutions(days: &[Day], is_release: bool) -> Result<(), Error> {
+        let mut cmd = Command::new("cargo");
+        cmd.args(["build", "--quiet"]);
+
+        if is_release {
+            cmd.arg("--release");
+        }
+
+        for day in days {
+            if Path::new(&get_path_for_bin(*day)).exists() {
+                cmd.args(["--bin", &day.to_string()]);
+            }
+        }
+
+        let status = cmd.status()?;
+        if !status.success() {
+            return Err(Error::BuildFailed(status));
+        }
+        Ok(())
+    }
+
+    /// Run the solution bin for a given day, capturing its output instead of forwarding it.
+    pub fn run_solution_buffered(
+        day: Day,
+        is_release: bool,
+        child_args: &[String],
+        limits: &RunLimits,
+    ) -> Result<SolutionOutput, Error> {
+        run(day, is_release, child_args, limits, false)
+    }
+
+    /// Run the solution bin for a given day, forwarding its output while it runs.
+    pub fn run_solution(
+        day: Day,
+        is_release: bool,
+        child_args: &[String],
+        limits: &RunLimits,
+    ) -> Result<SolutionOutput, Error> {
+        run(day, is_release, child_args, limits, true)
+    }
+
+    fn run(
+        day: Day,
+        is_release: bool,
+        child_args: &[String],
+        limits: &RunLimits,
+        forward: bool,
+    ) -> Result<SolutionOutput, Error> {
         // skip command invocation for days that have not been scaffolded yet.
         if !Path::new(&get_path_for_bin(day)).exists() {
-            return Ok(vec![]);
+            return Ok(SolutionOutput::default());
         }
 
-        let day_padded = day.to_string();
-        let mut args = vec!["run", "--quiet", "--bin", &day_padded];
+        run_command(
+            solution_command(day, is_release, child_args),
+            limits,
+            forward,
+        )
+    }
 
-        if is_release {
-            args.push("--release");
+    /// Run a command under `limits`, collecting its output and everything that went wrong.
+    fn run_command(
+        mut cmd: Command,
+        limits: &RunLimits,
+        forward: bool,
+    ) -> Result<SolutionOutput, Error> {
+        // On Linux, the command gets a process group of its own, so that a timeout also stops any
+        // processes it started. Those would otherwise keep the pipes below open. Being outside the
+        // terminal's process group, it no longer sees Ctrl-C, so it is killed when we exit instead.
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::process::CommandExt;
+
+            cmd.process_group(0);
+            // SAFETY: `prctl` is async-signal-safe and touches no memory of ours.
+            unsafe {
+                cmd.pre_exec(|| {
+                    if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
         }
 
-        if is_timed {
-            // mirror `--time` flag to child invocations.
-            args.push("--");
-            args.push("--time");
-        }
+        let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
 
-        // spawn child command with piped stdout/stderr.
-        // forward output to stdout/stderr while grabbing stdout lines.
+        let stdout = BufReader::new(child.stdout.take().ok_or(Error::BrokenPipe)?);
+        let stderr = BufReader::new(child.stderr.take().ok_or(Error::BrokenPipe)?);
 
-        let mut cmd = Command::new("cargo")
-            .args(&args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let print: fn(&str) = |line| println!("{line}");
+        let eprint: fn(&str) = |line| eprintln!("{line}");
+        let stdout = read_lines(stdout, forward.then_some(print));
+        let stderr = read_lines(stderr, forward.then_some(eprint));
 
-        let stdout = BufReader::new(cmd.stdout.take().ok_or(super::Error::BrokenPipe)?);
-        let stderr = BufReader::new(cmd.stderr.take().ok_or(super::Error::BrokenPipe)?);
+        let status = match limits.timeout {
+            Some(timeout) => wait_with_timeout(&mut child, timeout)?,
+            None => Some(child.wait()?),
+        };
 
-        let mut output = vec![];
+        let stdout = stdout.join().map_err(|_| Error::BrokenPipe)?;
+        let stderr = stderr.join().map_err(|_| Error::BrokenPipe)?;
+
+        let mut failures = parse_panics(&stdout);
+        match status {
+            None => failures.extend(limits.timeout.map(Failure::TimedOut)),
+            Some(_)
+                if stderr
+                    .iter()
+                    .any(|line| line.starts_with("memory allocation of")) =>
+            {
+                // printed by the default allocation error handler before aborting.
+                failures.push(Failure::OutOfMemory(limits.memory_limit));
+            }
+            // the runner exits with the panic exit code once parts have panicked.
+            Some(status) if status.code() == Some(101) && !failures.is_empty() => {}
+            Some(status) if !status.success() => {
+                failures.push(Failure::Crashed(status.to_string()))
+            }
+            Some(_) => {}
+        }
 
-        let thread = thread::spawn(move || {
-            stderr.lines().for_each(|line| {
-                eprintln!("{}", line.unwrap());
-            });
-        });
+        Ok(SolutionOutput {
+            stdout,
+            stderr,
+            failures,
+        })
+    }
+
+    /// Collect the lines of a pipe on a separate thread, optionally forwarding each one.
+    fn read_lines(
+        reader: impl BufRead + Send + 'static,
+        forward: Option<fn(&str)>,
+    ) -> thread::JoinHandle<Vec<String>> {
+        thread::spawn(move || {
+            reader
+                .lines()
+                .map_while(Result::ok)
+                .inspect(|line| forward.iter().for_each(|forward| forward(line)))
+                .collect()
+        })
+    }
+
+    /// Wait for `child` to exit, killing it once `timeout` has passed. On Linux the whole process
+    /// group of `child` is killed; elsewhere only `child` itself, not processes it started.
+    /// Returns its exit status, or `None` if it had to be killed.
+    fn wait_with_timeout(
+        child: &mut Child,
+        timeout: Duration,
+    ) -> Result<Option<ExitStatus>, Error> {
+        let start = Instant::now();
+
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
 
-        for line in stdout.lines() {
-            let line = line.unwrap();
-            println!("{line}");
-            output.push(line);
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                kill(child)?;
+                child.wait()?;
+                return Ok(None);
+            }
+            thread::sleep(POLL_INTERVAL.min(timeout - elapsed));
         }
+    }
 
-        thread.join().unwrap();
-        cmd.wait()?;
+    /// Kill `child` along with the process group [`run_command`] put it in.
+    #[cfg(target_os = "linux")]
+    fn kill(child: &mut Child) -> Result<(), Error> {
+        let pgid = libc::pid_t::try_from(child.id()).map_err(|_| Error::BrokenPipe)?;
+        // SAFETY: `kill` only sends a signal, to the group led by our own child.
+        if unsafe { libc::kill(-pgid, libc::SIGKILL) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
 
-        Ok(output)
+    #[cfg(not(target_os = "linux"))]
+    fn kill(child: &mut Child) -> Result<(), Error> {
+        Ok(child.kill()?)
+    }
+
+    /// Parses the lines the runner prints in place of the result of a part that panicked.
+    pub fn parse_panics(output: &[String]) -> Vec<Failure> {
+        output
+            .iter()
+            .filter_map(|l| {
+                // earlier output on the line is overwritten by a carriage return.
+                let line = l.rsplit('\r').next()?;
+                let (part, panic) = line.strip_prefix("Part ")?.split_once(": ✖ panicked at ")?;
+                let (location, message) = panic.split_once(": ")?;
+
+                Some(Failure::Panicked {
+                    part: part.parse().ok()?,
+                    location: location.to_string(),
+                    message: message.to_string(),
+                })
+            })
+            .collect()
     }
 
     pub fn parse_exec_time(output: &[String], day: Day) -> super::Timing {
@@ -133,7 +605,6 @@ pub mod child_commands {
             day,
             part_1: None,
             part_2: None,
-            total_nanos: 0_f64,
         };
 
         output
@@ -143,50 +614,45 @@ pub mod child_commands {
                     return None;
                 }
 
-                let Some((timing_str, nanos)) = parse_time(l) else {
+                let Some(part_timing) = parse_time(l) else {
                     eprintln!("Could not parse timings from line: {l}");
                     return None;
                 };
 
                 let part = l.split(':').next()?;
-                Some((part, timing_str, nanos))
+                Some((part, part_timing))
             })
-            .for_each(|(part, timing_str, nanos)| {
+            .for_each(|(part, part_timing)| {
                 if part.contains("Part 1") {
-                    timings.part_1 = Some(timing_str.into());
+                    timings.part_1 = Some(part_timing);
                 } else if part.contains("Part 2") {
-                    timings.part_2 = Some(timing_str.into());
+                    timings.part_2 = Some(part_timing);
                 }
-
-                timings.total_nanos += nanos;
             });
 
         timings
     }
 
-    fn parse_to_float(s: &str, postfix: &str) -> Option<f64> {
-        s.split(postfix).next()?.parse().ok()
-    }
-
-    fn parse_time(line: &str) -> Option<(&str, f64)> {
-        // for possible time formats, see: https://github.com/rust-lang/rust/blob/1.64.0/library/core/src/time.rs#L1176-L1200
-        let str_timing = line
+    /// Parses the `(mean ± spread @ N samples)` suffix the runner prints after a result.
+    /// The spread is optional, for output from runners that do not report it.
+    fn parse_time(line: &str) -> Option<PartTiming> {
+        let (stats, samples) = line
             .split(" samples)")
             .next()?
-            .split('(')
-            .last()?
-            .split('@')
-            .next()?
-            .trim();
+            .rsplit_once('(')?
+            .1
+            .split_once('@')?;
 
-        let parsed_timing = match str_timing {
-            s if s.contains("ns") => s.split("ns").next()?.parse::<f64>().ok(),
-            s if s.contains("µs") => parse_to_float(s, "µs").map(|x| x * 1000_f64),
-            s if s.contains("ms") => parse_to_float(s, "ms").map(|x| x * 1_000_000_f64),
-            s => parse_to_float(s, "s").map(|x| x * 1_000_000_000_f64),
-        }?;
+        let (mean, spread) = match stats.split_once('±') {
+            Some((mean, spread)) => (mean, Some(spread)),
+            None => (stats, None),
+        };
 
-        Some((str_timing, parsed_timing))
+        Some(PartTiming {
+            nanos: parse_duration(mean.trim())?,
+            samples: samples.trim().parse().ok()?,
+            spread_nanos: spread.map_or(Some(0_f64), |s| parse_duration(s.trim()))?,
+        })
     }
 
     /// copied from: https://github.com/rust-lang/rust/blob/1.64.0/library/std/src/macros.rs#L328-L333
@@ -205,9 +671,121 @@ pub mod child_commands {
 
     #[cfg(feature = "test_lib")]
     mod tests {
-        use super::parse_exec_time;
+        #[cfg(unix)]
+        use super::run_command;
+        use super::{parse_exec_time, parse_panics};
 
         use crate::day;
+        #[cfg(unix)]
+        use crate::template::bench::RunLimits;
+        use crate::template::run_multi::Failure;
+        #[cfg(unix)]
+        use std::{process::Command, time::Duration};
+
+        #[cfg(unix)]
+        fn sh(script: &str) -> Command {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", script]);
+            cmd
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn reports_failing_children() {
+            let limits = RunLimits::default();
+
+            let output = run_command(sh("echo 'Part 1: 1 (1.0ms)'"), &limits, false).unwrap();
+            assert_eq!(output.stdout, ["Part 1: 1 (1.0ms)"]);
+            assert!(output.failures.is_empty());
+
+            let output = run_command(sh("echo oops >&2; exit 3"), &limits, false).unwrap();
+            assert_eq!(output.stderr, ["oops"]);
+            assert_eq!(output.failures, [Failure::Crashed("exit status: 3".into())]);
+
+            let output = run_command(sh("kill -SEGV $$"), &limits, false).unwrap();
+            assert!(
+                matches!(&output.failures[..], [Failure::Crashed(status)] if status.contains("SIGSEGV"))
+            );
+
+            // parts that panicked are reported on their own, not as a crash.
+            let panicked = "echo 'Part 1: ✖ panicked at src/bin/01.rs:1:1: oops'; exit 101";
+            let output = run_command(sh(panicked), &limits, false).unwrap();
+            assert!(matches!(
+                &output.failures[..],
+                [Failure::Panicked { part: 1, .. }]
+            ));
+
+            let output = run_command(
+                sh("echo 'memory allocation of 8 bytes failed' >&2; kill -ABRT $$"),
+                &limits,
+                false,
+            )
+            .unwrap();
+            assert_eq!(output.failures, [Failure::OutOfMemory(None)]);
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn kills_children_on_timeout() {
+            let limits = RunLimits {
+                timeout: Some(Duration::from_millis(50)),
+                memory_limit: None,
+            };
+            let output = run_command(sh("exec sleep 5"), &limits, false).unwrap();
+            assert_eq!(
+                output.failures,
+                [Failure::TimedOut(Duration::from_millis(50))]
+            );
+        }
+
+        #[test]
+        #[cfg(target_os = "linux")]
+        fn kills_grandchildren_on_timeout() {
+            let limits = RunLimits {
+                timeout: Some(Duration::from_millis(50)),
+                memory_limit: None,
+            };
+
+            // the shell stays the parent of `sleep`, which holds on to its stdout.
+            let start = std::time::Instant::now();
+            let output = run_command(sh("sleep 5; echo done"), &limits, false).unwrap();
+            assert_eq!(
+                output.failures,
+                [Failure::TimedOut(Duration::from_millis(50))]
+            );
+            assert!(output.stdout.is_empty());
+            assert!(start.elapsed() < Duration::from_secs(2));
+        }
+
+        #[test]
+        fn parses_panics() {
+            let res = parse_panics(&[
+                "Part 1: ✖ panicked at src/bin/06.rs:12:18: Invalid character in input".into(),
+                "Part 2: 42 > benching\rPart 2: ✖ panicked at src/bin/06.rs:40:9: oops: a: b"
+                    .into(),
+                "Part 1: 0 (74.13ns @ 100000 samples)".into(),
+                "Part 2: ✖             ".into(),
+            ]);
+            assert_eq!(
+                res,
+                [
+                    Failure::Panicked {
+                        part: 1,
+                        location: "src/bin/06.rs:12:18".into(),
+                        message: "Invalid character in input".into(),
+                    },
+                    Failure::Panicked {
+                        part: 2,
+                        location: "src/bin/06.rs:40:9".into(),
+                        message: "oops: a: b".into(),
+                    },
+                ]
+            );
+            assert_eq!(
+                res[0].to_string(),
+                "part 1 panicked at src/bin/06.rs:12:18: Invalid character in input"
+            );
+        }
 
         #[test]
         fn parses_execution_times() {
@@ -219,9 +797,11 @@ pub mod child_commands {
                 ],
                 day!(1),
             );
-            assert_approx_eq!(res.total_nanos, 74130074.13_f64);
-            assert_eq!(res.part_1.unwrap(), "74.13ns");
-            assert_eq!(res.part_2.unwrap(), "74.13ms");
+            assert_approx_eq!(res.total_nanos(), 74130074.13_f64);
+            assert_approx_eq!(res.part_1.unwrap().nanos, 74.13_f64);
+            assert_eq!(res.part_1.unwrap().samples, 100000);
+            assert_approx_eq!(res.part_2.unwrap().nanos, 74130000_f64);
+            assert_eq!(res.part_2.unwrap().samples, 99999);
         }
 
         #[test]
@@ -234,9 +814,42 @@ pub mod child_commands {
                 ],
                 day!(1),
             );
-            assert_approx_eq!(res.total_nanos, 2100000000_f64);
-            assert_eq!(res.part_1.unwrap(), "2s");
-            assert_eq!(res.part_2.unwrap(), "100ms");
+            assert_approx_eq!(res.total_nanos(), 2100000000_f64);
+            assert_approx_eq!(res.part_1.unwrap().nanos, 2000000000_f64);
+            assert_approx_eq!(res.part_2.unwrap().nanos, 100000000_f64);
+        }
+
+        #[test]
+        fn parses_single_sample() {
+            let res = parse_exec_time(
+                &[
+                    "Part 1: 42 > benching\rPart 1: 42 (1.2ms ± 0.0ns @ 1 samples)".into(),
+                    "Part 2: 43 (3.0µs ± 0.0ns @ 1 samples)".into(),
+                    "".into(),
+                ],
+                day!(1),
+            );
+            let part_1 = res.part_1.unwrap();
+            assert_approx_eq!(part_1.nanos, 1200000_f64);
+            assert_eq!(part_1.samples, 1);
+            assert_approx_eq!(res.part_2.unwrap().nanos, 3000_f64);
+        }
+
+        #[test]
+        fn parses_spread() {
+            let res = parse_exec_time(
+                &[
+                    "Part 1: 42 (1.5ms ± 120.0µs @ 667 samples)".into(),
+                    "Part 2: 43 (2.0ms ± 0.0ns @ 10 samples)".into(),
+                    "".into(),
+                ],
+                day!(1),
+            );
+            let part_1 = res.part_1.unwrap();
+            assert_approx_eq!(part_1.nanos, 1500000_f64);
+            assert_approx_eq!(part_1.spread_nanos, 120000_f64);
+            assert_eq!(part_1.samples, 667);
+            assert_approx_eq!(res.part_2.unwrap().spread_nanos, 0_f64);
         }
 
         #[test]
@@ -249,9 +862,41 @@ pub mod child_commands {
                 ],
                 day!(1),
             );
-            assert_approx_eq!(res.total_nanos, 0_f64);
+            assert_approx_eq!(res.total_nanos(), 0_f64);
             assert_eq!(res.part_1.is_none(), true);
             assert_eq!(res.part_2.is_none(), true);
         }
     }
 }
+
+#[cfg(feature = "test_lib")]
+mod tests {
+    use super::{is_parallel, InOrder};
+    use crate::day;
+
+    #[test]
+    fn releases_results_in_day_order() {
+        let mut in_order = InOrder::new(&[day!(1), day!(3), day!(4), day!(9)]);
+
+        assert!(in_order.push(day!(4), "d").is_empty());
+        assert!(in_order.push(day!(3), "c").is_empty());
+        assert_eq!(
+            in_order.push(day!(1), "a"),
+            vec![(day!(1), "a"), (day!(3), "c"), (day!(4), "d")]
+        );
+        assert_eq!(in_order.push(day!(9), "i"), vec![(day!(9), "i")]);
+    }
+
+    #[test]
+    fn detects_parallel_solutions() {
+        let stdout = |lines: &[&str]| lines.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert!(is_parallel(&stdout(&[
+            "\x1b[3mParallel on 8 threads\x1b[0m",
+            "Part 1: 42 (1.0ms ± 0.1ms @ 10 samples)",
+        ])));
+        assert!(!is_parallel(&stdout(&[
+            "Part 1: 42 (1.0ms ± 0.1ms @ 10 samples)"
+        ])));
+    }
+}
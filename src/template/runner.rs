@@ -2,34 +2,169 @@
 use std::fmt::Display;
 use std::hint::black_box;
 use std::io::{stdout, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::process::Output;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Once};
 use std::time::{Duration, Instant};
-use std::{cmp, env, process};
+use std::{env, process};
 
+use crate::template::bench::{parse_duration_arg, BenchConfig, BenchFile, BenchOverrides};
 use crate::template::ANSI_BOLD;
 use crate::template::{aoc_cli, Day, ANSI_ITALIC, ANSI_RESET};
 
+/// Location and message of the first panic since it was last taken.
+static LAST_PANIC: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// Whether any part of this run panicked.
+static ANY_PANICKED: AtomicBool = AtomicBool::new(false);
+
+/// Run a part and print its result. If the part panics, the panic is printed in place of the
+/// result and the remaining parts still run.
 pub fn run_part<I: Clone, T: Display>(func: impl Fn(I) -> Option<T>, input: I, day: Day, part: u8) {
+    configure_threads();
+    limit_memory();
+    capture_panics();
+
     let part_str = format!("Part {part}");
 
-    let (result, duration, samples) =
-        run_timed(func, input, |result| print_result(result, &part_str, ""));
+    let run = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_timed(func, input, day, |result| {
+            print_result(result, &part_str, "")
+        })
+    }));
 
-    print_result(&result, &part_str, &format_duration(&duration, samples));
+    let Ok((result, duration, bench_stats)) = run else {
+        let (location, message) = LAST_PANIC
+            .lock()
+            .ok()
+            .and_then(|mut last| last.take())
+            .unwrap_or_else(|| ("unknown location".into(), "unknown panic".into()));
+        print_panic(&part_str, &location, &message);
+        ANY_PANICKED.store(true, Ordering::Relaxed);
+        return;
+    };
+
+    print_result(&result, &part_str, &format_duration(&duration, bench_stats));
 
     if let Some(result) = result {
         submit_result(result, day, part);
     }
 }
 
+/// Printed by solutions marked `parallel` when timed, followed by the size of the rayon pool.
+pub const PARALLEL_MARKER: &str = "Parallel on";
+
+/// Tell `time` that this solution parallelizes, so that it can also be timed on one thread.
+pub fn print_parallel() {
+    if env::args().any(|x| x == "--time") {
+        configure_threads();
+        let threads = match rayon::current_num_threads() {
+            1 => "1 thread".to_string(),
+            n => format!("{n} threads"),
+        };
+        println!("{ANSI_ITALIC}{PARALLEL_MARKER} {threads}{ANSI_RESET}");
+    }
+}
+
+/// Exit with a non-zero code if any part panicked, once every part has run.
+pub fn exit_if_failed() {
+    if ANY_PANICKED.load(Ordering::Relaxed) {
+        process::exit(101);
+    }
+}
+
+/// Record panics for [`run_part`] to print instead of printing them from the panicking thread.
+/// The default output is kept when `RUST_BACKTRACE` is set, so that backtraces stay available.
+fn capture_panics() {
+    static HOOK: Once = Once::new();
+
+    HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            let location = info
+                .location()
+                .map_or_else(|| "unknown location".into(), ToString::to_string);
+            let message = info.payload_as_str().unwrap_or("Box<dyn Any>").to_string();
+
+            // keep the first panic, should several rayon workers panic before the part stops.
+            if let Ok(mut last) = LAST_PANIC.lock() {
+                last.get_or_insert((location, message));
+            }
+
+            if env::var_os("RUST_BACKTRACE").is_some() {
+                default_hook(info);
+            }
+        }));
+    });
+}
+
+/// Size the rayon global pool from a `--threads N` argument.
+/// The pool can only be built once, so the size set before the first part is kept for the rest.
+fn configure_threads() {
+    let args: Vec<String> = env::args().collect();
+    let Some(i) = args.iter().position(|x| x == "--threads") else {
+        return;
+    };
+
+    let Some(threads) = args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) else {
+        eprintln!("Unexpected command-line input. Format: cargo solve 1 --threads 4");
+        process::exit(1);
+    };
+
+    let _ = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global();
+}
+
+/// Cap the address space of this process from a `--memory-limit BYTES` argument, so that a
+/// runaway solution fails to allocate instead of exhausting the machine. Only enforced on Linux.
+fn limit_memory() {
+    static LIMIT: Once = Once::new();
+
+    LIMIT.call_once(|| {
+        let args: Vec<String> = env::args().collect();
+        let Some(i) = args.iter().position(|x| x == "--memory-limit") else {
+            return;
+        };
+
+        let Some(bytes) = args.get(i + 1).and_then(|n| n.parse::<u64>().ok()) else {
+            eprintln!("Unexpected command-line input. Format: --memory-limit 1073741824");
+            process::exit(1);
+        };
+
+        #[cfg(target_os = "linux")]
+        {
+            let limit = libc::rlimit {
+                rlim_cur: bytes,
+                rlim_max: bytes,
+            };
+            // SAFETY: `setrlimit` only reads the struct it is given.
+            if unsafe { libc::setrlimit(libc::RLIMIT_AS, &limit) } != 0 {
+                eprintln!(
+                    "Could not apply memory limit: {}",
+                    std::io::Error::last_os_error()
+                );
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        eprintln!("Memory limits are only enforced on Linux, ignoring `--memory-limit {bytes}`.");
+    });
+}
+
 /// Run a solution part. The behavior differs depending on whether we are running a release or debug build:
 ///  1. in debug, the function is executed once.
-///  2. in release, the function is benched (approx. 1 second of execution time or 10 samples, whatever take longer.)
+///  2. in release, the function is benched within the budget and iteration bounds set in [`BenchConfig`].
+///
+/// Returns the result, the mean duration and, if benched, the spread and number of samples.
 fn run_timed<I: Clone, T>(
     func: impl Fn(I) -> T,
     input: I,
+    day: Day,
     hook: impl Fn(&T),
-) -> (T, Duration, u128) {
+) -> (T, Duration, Option<(Duration, u128)>) {
     let timer = Instant::now();
     let result = {
         let input = input.clone();
@@ -43,27 +178,54 @@ fn run_timed<I: Clone, T>(
 
     hook(&result);
 
-    let run = if std::env::args().any(|x| x == "--time") {
-        bench(func, input, &base_time)
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|x| x == "--time") {
+        let overrides = BenchOverrides::from_args(&args).unwrap_or_else(|e| {
+            eprintln!("Unexpected command-line input: {e}");
+            process::exit(1);
+        });
+        let config = BenchConfig::resolve(&BenchFile::read_from_file(), day, &overrides);
+        let (duration, spread, samples) = bench(func, input, &base_time, &config, deadline(&args));
+        (result, duration, Some((spread, samples)))
     } else {
-        (base_time, 1)
-    };
+        (result, base_time, None)
+    }
+}
 
-    (result, run.0, run.1)
+/// The point in time when benching must stop, passed by `time` to stay within its total budget.
+fn deadline(args: &[String]) -> Option<Instant> {
+    let i = args.iter().position(|x| x == "--deadline")?;
+    let time_left = parse_duration_arg(args.get(i + 1)?).ok()?;
+    Some(Instant::now() + time_left)
 }
 
-fn bench<I: Clone, T>(func: impl Fn(I) -> T, input: I, base_time: &Duration) -> (Duration, u128) {
+/// Returns the mean duration, the standard deviation and the number of samples taken.
+/// Stops early once `deadline` has passed, keeping at least one sample.
+fn bench<I: Clone, T>(
+    func: impl Fn(I) -> T,
+    input: I,
+    base_time: &Duration,
+    config: &BenchConfig,
+    deadline: Option<Instant>,
+) -> (Duration, Duration, u128) {
     let mut stdout = stdout();
 
     print!(" > {ANSI_ITALIC}benching{ANSI_RESET}");
     let _ = stdout.flush();
 
-    let bench_iterations =
-        (Duration::from_secs(1).as_nanos() / cmp::max(base_time.as_nanos(), 10)).clamp(10, 10000);
+    for _ in 0..config.warmup {
+        black_box(func(black_box(input.clone())));
+    }
+
+    let bench_iterations = config.iterations(*base_time);
 
     let mut timers: Vec<Duration> = vec![];
 
     for _ in 0..bench_iterations {
+        if !timers.is_empty() && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+
         // need a clone here to make the borrow checker happy.
         let cloned = input.clone();
         let timer = Instant::now();
@@ -71,10 +233,13 @@ fn bench<I: Clone, T>(func: impl Fn(I) -> T, input: I, base_time: &Duration) ->
         timers.push(timer.elapsed());
     }
 
+    let average = average_duration(&timers);
+
     (
         #[allow(clippy::cast_possible_truncation)]
-        Duration::from_nanos(average_duration(&timers) as u64),
-        bench_iterations,
+        Duration::from_nanos(average as u64),
+        Duration::from_nanos(standard_deviation(&timers, average) as u64),
+        timers.len() as u128,
     )
 }
 
@@ -86,11 +251,22 @@ fn average_duration(numbers: &[Duration]) -> u128 {
         / numbers.len() as u128
 }
 
-fn format_duration(duration: &Duration, samples: u128) -> String {
-    if samples == 1 {
-        format!(" ({duration:.1?})")
-    } else {
-        format!(" ({duration:.1?} @ {samples} samples)")
+fn standard_deviation(numbers: &[Duration], average: u128) -> f64 {
+    let variance = numbers
+        .iter()
+        .map(|d| (d.as_nanos() as f64 - average as f64).powi(2))
+        .sum::<f64>()
+        / numbers.len() as f64;
+    variance.sqrt()
+}
+
+/// Benched parts always report their samples, even a single one, as `run_multi` parses them.
+fn format_duration(duration: &Duration, bench_stats: Option<(Duration, u128)>) -> String {
+    match bench_stats {
+        Some((spread, samples)) => {
+            format!(" ({duration:.1?} ± {spread:.1?} @ {samples} samples)")
+        }
+        None => format!(" ({duration:.1?})"),
     }
 }
 
@@ -129,6 +305,16 @@ fn print_result<T: Display>(result: &Option<T>, part: &str, duration_str: &str)
     }
 }
 
+/// Printed in place of a result. `run_multi` reads these lines back, so that it can report which
+/// parts failed.
+fn print_panic(part: &str, location: &str, message: &str) {
+    print!("\r");
+    println!(
+        "{part}: ✖ panicked at {location}: {}",
+        message.lines().collect::<Vec<_>>().join(" ")
+    );
+}
+
 /// Parse the arguments passed to `solve` and try to submit one part of the solution if:
 ///  1. we are in `--release` mode.
 ///  2. aoc-cli is installed.
@@ -1,17 +1,73 @@
-use std::{collections::HashMap, fs, io::Error, str::FromStr};
+use std::{collections::HashMap, fmt, fs, io::Error, str::FromStr, time::Duration};
 use tinyjson::JsonValue;
 
+use crate::template::machine::MachineInfo;
 use crate::template::Day;
 
 static TIMINGS_FILE_PATH: &str = "./data/timings.json";
 
+/// Version of the JSON layout written by [`Timings::store_file`].
+///
+/// 1. parts stored as formatted strings, plus a combined `total_nanos`. Files without a
+///    `version` key use this layout.
+/// 2. parts stored as objects with `nanos`, `samples` and `spread_nanos`, and an optional
+///    `machine` object describing where the last run was taken, and an optional
+///    `single_threaded` array of timings for parallel days run on one thread.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Benchmark result for a single part.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PartTiming {
+    /// Mean duration of a run.
+    pub nanos: f64,
+    /// Number of runs the mean was taken over, or 0 if unknown (migrated from schema version 1).
+    pub samples: u64,
+    /// Standard deviation of the runs.
+    pub spread_nanos: f64,
+}
+
+impl PartTiming {
+    pub fn duration(&self) -> Duration {
+        Duration::from_nanos(self.nanos as u64)
+    }
+
+    pub fn spread(&self) -> Duration {
+        Duration::from_nanos(self.spread_nanos as u64)
+    }
+}
+
+impl fmt::Display for PartTiming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1?}", self.duration())
+    }
+}
+
 /// Represents benchmark times for a single day.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Timing {
     pub day: Day,
-    pub part_1: Option<String>,
-    pub part_2: Option<String>,
-    pub total_nanos: f64,
+    pub part_1: Option<PartTiming>,
+    pub part_2: Option<PartTiming>,
+}
+
+impl Timing {
+    pub fn part(&self, part: u8) -> Option<&PartTiming> {
+        match part {
+            1 => self.part_1.as_ref(),
+            2 => self.part_2.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Combined duration of both parts.
+    pub fn total_nanos(&self) -> f64 {
+        [&self.part_1, &self.part_2]
+            .into_iter()
+            .flatten()
+            .map(|part| part.nanos)
+            // `sum` of no floats is `-0.0`, which would print as `-0.00ms`.
+            .fold(0_f64, |total, nanos| total + nanos)
+    }
 }
 
 /// Represents benchmark times for a set of days.
@@ -19,6 +75,10 @@ pub struct Timing {
 #[derive(Clone, Debug, Default)]
 pub struct Timings {
     pub data: Vec<Timing>,
+    /// The machine and toolchain of the most recent run, if recorded.
+    pub machine: Option<MachineInfo>,
+    /// Timings of days that use rayon, taken again with a single thread.
+    pub single_threaded: Vec<Timing>,
 }
 
 impl Timings {
@@ -30,6 +90,7 @@ impl Timings {
     }
 
     /// Rehydrate timings from a JSON file. If not present, returns empty timings.
+    /// Files in an older schema are migrated in memory, and rewritten on the next store.
     pub fn read_from_file() -> Self {
         let s = fs::read_to_string(TIMINGS_FILE_PATH)
             .map_err(|x| x.to_string())
@@ -46,25 +107,25 @@ impl Timings {
 
     /// Merge two sets of timings, overwriting `self` with `other` if present.
     pub fn merge(&self, new: &Self) -> Self {
-        let mut data: Vec<Timing> = vec![];
-
-        for timing in &new.data {
-            data.push(timing.clone());
-        }
-
-        for timing in &self.data {
-            if !data.iter().any(|t| t.day == timing.day) {
-                data.push(timing.clone());
-            }
+        Timings {
+            data: merge_by_day(&self.data, &new.data),
+            machine: new.machine.clone().or_else(|| self.machine.clone()),
+            single_threaded: merge_by_day(&self.single_threaded, &new.single_threaded),
         }
+    }
 
-        data.sort_unstable_by(|a, b| a.day.cmp(&b.day));
-        Timings { data }
+    /// The single-threaded timing of a day, if it was taken.
+    pub fn single_threaded(&self, day: Day) -> Option<&Timing> {
+        self.single_threaded.iter().find(|t| t.day == day)
     }
 
     /// Sum up total duration of timings as millis.
     pub fn total_millis(&self) -> f64 {
-        self.data.iter().map(|x| x.total_nanos).sum::<f64>() / 1_000_000_f64
+        self.data
+            .iter()
+            .map(Timing::total_nanos)
+            .fold(0_f64, |total, nanos| total + nanos)
+            / 1_000_000_f64
     }
 
     pub fn is_day_complete(&self, day: Day) -> bool {
@@ -74,16 +135,61 @@ impl Timings {
     }
 }
 
+fn merge_by_day(old: &[Timing], new: &[Timing]) -> Vec<Timing> {
+    let mut data: Vec<Timing> = vec![];
+
+    for timing in new {
+        data.push(timing.clone());
+    }
+
+    for timing in old {
+        if !data.iter().any(|t| t.day == timing.day) {
+            data.push(timing.clone());
+        }
+    }
+
+    data.sort_unstable_by_key(|a| a.day);
+    data
+}
+
+/// Parses a duration formatted with `{:?}`, such as `74.13ms`, into nanoseconds.
+pub fn parse_duration(s: &str) -> Option<f64> {
+    // for possible time formats, see: https://github.com/rust-lang/rust/blob/1.64.0/library/core/src/time.rs#L1176-L1200
+    match s {
+        s if s.contains("ns") => parse_to_float(s, "ns"),
+        s if s.contains("µs") => parse_to_float(s, "µs").map(|x| x * 1000_f64),
+        s if s.contains("ms") => parse_to_float(s, "ms").map(|x| x * 1_000_000_f64),
+        s => parse_to_float(s, "s").map(|x| x * 1_000_000_000_f64),
+    }
+}
+
+fn parse_to_float(s: &str, postfix: &str) -> Option<f64> {
+    s.split(postfix).next()?.parse().ok()
+}
+
 /* -------------------------------------------------------------------------- */
 
 impl From<Timings> for JsonValue {
     fn from(value: Timings) -> Self {
         let mut map: HashMap<String, JsonValue> = HashMap::new();
 
+        map.insert(
+            "version".into(),
+            JsonValue::Number(f64::from(SCHEMA_VERSION)),
+        );
         map.insert(
             "data".into(),
             JsonValue::Array(value.data.iter().map(JsonValue::from).collect()),
         );
+        if let Some(machine) = &value.machine {
+            map.insert("machine".into(), JsonValue::from(machine));
+        }
+        if !value.single_threaded.is_empty() {
+            map.insert(
+                "single_threaded".into(),
+                JsonValue::Array(value.single_threaded.iter().map(JsonValue::from).collect()),
+            );
+        }
 
         JsonValue::Object(map)
     }
@@ -94,60 +200,120 @@ impl TryFrom<String> for Timings {
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         let json = JsonValue::from_str(&value).or(Err("not valid JSON file."))?;
+        Timings::try_from(&json)
+    }
+}
 
-        let json_data = json
+impl TryFrom<&JsonValue> for Timings {
+    type Error = String;
+
+    fn try_from(json: &JsonValue) -> Result<Self, Self::Error> {
+        let json = json
             .get::<HashMap<String, JsonValue>>()
-            .ok_or("expected JSON document to be an object.")?
+            .ok_or("expected JSON document to be an object.")?;
+
+        let version = match json.get("version") {
+            None => 1,
+            Some(v) => v
+                .get::<f64>()
+                .map(|&v| v as u32)
+                .ok_or("expected `json.version` to be a number.")?,
+        };
+        if version == 0 || version > SCHEMA_VERSION {
+            return Err(format!(
+                "unsupported timings schema version {version}, expected at most {SCHEMA_VERSION}."
+            ));
+        }
+
+        let json_data = json
             .get("data")
             .ok_or("expected JSON document to have key `data`.")?
             .get::<Vec<JsonValue>>()
             .ok_or("expected `json.data` to be an array.")?;
 
+        let single_threaded = match json.get("single_threaded") {
+            None => vec![],
+            Some(v) => v
+                .get::<Vec<JsonValue>>()
+                .ok_or("expected `json.single_threaded` to be an array.")?
+                .iter()
+                .map(|timing| Timing::from_json(timing, version))
+                .collect::<Result<_, _>>()?,
+        };
+
+        let machine = json
+            .get("machine")
+            .filter(|v| !v.is_null())
+            .map(MachineInfo::try_from)
+            .transpose()?;
+
         Ok(Timings {
             data: json_data
                 .iter()
-                .map(Timing::try_from)
+                .map(|timing| Timing::from_json(timing, version))
                 .collect::<Result<_, _>>()?,
+            machine,
+            single_threaded,
         })
     }
 }
 
 /* -------------------------------------------------------------------------- */
 
+impl From<&PartTiming> for JsonValue {
+    fn from(value: &PartTiming) -> Self {
+        let mut map: HashMap<String, JsonValue> = HashMap::new();
+
+        map.insert("nanos".into(), JsonValue::Number(value.nanos));
+        map.insert("samples".into(), JsonValue::Number(value.samples as f64));
+        map.insert("spread_nanos".into(), JsonValue::Number(value.spread_nanos));
+
+        JsonValue::Object(map)
+    }
+}
+
+impl TryFrom<&JsonValue> for PartTiming {
+    type Error = String;
+
+    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+        let json = value
+            .get::<HashMap<String, JsonValue>>()
+            .ok_or("Expected part timing to be a JSON object.")?;
+
+        let number = |key: &str| {
+            json.get(key)
+                .and_then(|v| v.get::<f64>().copied())
+                .ok_or(format!("Expected part timing.{key} to be a number."))
+        };
+
+        Ok(PartTiming {
+            nanos: number("nanos")?,
+            samples: number("samples")? as u64,
+            spread_nanos: number("spread_nanos")?,
+        })
+    }
+}
+
 impl From<&Timing> for JsonValue {
     fn from(value: &Timing) -> Self {
         let mut map: HashMap<String, JsonValue> = HashMap::new();
 
         map.insert("day".into(), JsonValue::String(value.day.to_string()));
-        map.insert("total_nanos".into(), JsonValue::Number(value.total_nanos));
-
-        let part_1 = value.part_1.clone().map(JsonValue::String);
-        let part_2 = value.part_2.clone().map(JsonValue::String);
-
-        map.insert(
-            "part_1".into(),
-            match part_1 {
-                Some(x) => x,
-                None => JsonValue::Null,
-            },
-        );
 
-        map.insert(
-            "part_2".into(),
-            match part_2 {
-                Some(x) => x,
-                None => JsonValue::Null,
-            },
-        );
+        for (key, part) in [("part_1", &value.part_1), ("part_2", &value.part_2)] {
+            map.insert(
+                key.into(),
+                part.as_ref().map_or(JsonValue::Null, JsonValue::from),
+            );
+        }
 
         JsonValue::Object(map)
     }
 }
 
-impl TryFrom<&JsonValue> for Timing {
-    type Error = String;
-
-    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+impl Timing {
+    /// Reads a timing written in the given schema version.
+    fn from_json(value: &JsonValue, version: u32) -> Result<Self, String> {
         let json = value
             .get::<HashMap<String, JsonValue>>()
             .ok_or("Expected timing to be a JSON object.")?;
@@ -158,76 +324,132 @@ impl TryFrom<&JsonValue> for Timing {
             .and_then(|day| Day::from_str(day).ok())
             .ok_or("Expected timing.day to be a Day struct.")?;
 
-        let part_1 = json
-            .get("part_1")
-            .map(|v| if v.is_null() { None } else { v.get::<String>() })
-            .ok_or("Expected timing.part_1 to be null or string.")?;
+        let mut parts = [None, None];
+        for (part, key) in parts.iter_mut().zip(["part_1", "part_2"]) {
+            let v = json
+                .get(key)
+                .ok_or(format!("Expected timing.{key} to be present."))?;
+            if v.is_null() {
+                continue;
+            }
 
-        let part_2 = json
-            .get("part_2")
-            .map(|v| if v.is_null() { None } else { v.get::<String>() })
-            .ok_or("Expected timing.part_2 to be null or string.")?;
+            *part = Some(if version == 1 {
+                migrate_part_v1(v).ok_or(format!(
+                    "Expected timing.{key} to be null or a duration string."
+                ))?
+            } else {
+                PartTiming::try_from(v)?
+            });
+        }
 
-        let total_nanos = json
-            .get("total_nanos")
-            .and_then(|v| v.get::<f64>().copied())
-            .ok_or("Expected timing.total_nanos to be a number.")?;
+        if version == 1 {
+            // `total_nanos` is derived from the parts since version 2, but still validated here
+            json.get("total_nanos")
+                .and_then(|v| v.get::<f64>())
+                .ok_or("Expected timing.total_nanos to be a number.")?;
+        }
 
+        let [part_1, part_2] = parts;
         Ok(Timing {
             day,
-            part_1: part_1.cloned(),
-            part_2: part_2.cloned(),
-            total_nanos,
+            part_1,
+            part_2,
         })
     }
 }
 
+/// Version 1 only kept the formatted mean, so the sample count and spread are unknown.
+fn migrate_part_v1(value: &JsonValue) -> Option<PartTiming> {
+    let nanos = parse_duration(value.get::<String>()?)?;
+    Some(PartTiming {
+        nanos,
+        samples: 0,
+        spread_nanos: 0.0,
+    })
+}
+
 /* -------------------------------------------------------------------------- */
 
 #[cfg(feature = "test_lib")]
 mod tests {
     use crate::day;
 
-    use super::{Timing, Timings};
+    use super::{PartTiming, Timing, Timings};
+
+    fn part(millis: f64) -> PartTiming {
+        PartTiming {
+            nanos: millis * 1_000_000_f64,
+            samples: 100,
+            spread_nanos: 1_000_f64,
+        }
+    }
 
     fn get_mock_timings() -> Timings {
         Timings {
             data: vec![
                 Timing {
                     day: day!(1),
-                    part_1: Some("10ms".into()),
-                    part_2: Some("20ms".into()),
-                    total_nanos: 3e+10,
+                    part_1: Some(part(10.0)),
+                    part_2: Some(part(20.0)),
                 },
                 Timing {
                     day: day!(2),
-                    part_1: Some("30ms".into()),
-                    part_2: Some("40ms".into()),
-                    total_nanos: 7e+10,
+                    part_1: Some(part(30.0)),
+                    part_2: Some(part(40.0)),
                 },
                 Timing {
                     day: day!(4),
-                    part_1: Some("40ms".into()),
+                    part_1: Some(part(40.0)),
                     part_2: None,
-                    total_nanos: 4e+10,
                 },
             ],
+            machine: None,
+            single_threaded: vec![],
         }
     }
 
     mod deserialization {
-        use crate::{day, template::timings::Timings};
+        use crate::{
+            day,
+            template::timings::{PartTiming, Timings},
+        };
 
         #[test]
         fn handles_json_timings() {
+            let json = r#"{ "version": 2, "data": [{ "day": "01", "part_1": { "nanos": 1500, "samples": 10, "spread_nanos": 20 }, "part_2": null }] }"#.to_string();
+            let timings = Timings::try_from(json).unwrap();
+            assert_eq!(timings.data.len(), 1);
+            let timing = timings.data.first().unwrap();
+            assert_eq!(timing.day, day!(1));
+            assert_eq!(
+                timing.part_1,
+                Some(PartTiming {
+                    nanos: 1500_f64,
+                    samples: 10,
+                    spread_nanos: 20_f64,
+                })
+            );
+            assert_eq!(timing.part_2, None);
+            assert_eq!(timing.total_nanos(), 1500_f64);
+        }
+
+        #[test]
+        fn migrates_version_1_timings() {
             let json = r#"{ "data": [{ "day": "01", "part_1": "1ms", "part_2": null, "total_nanos": 1000000000 }] }"#.to_string();
             let timings = Timings::try_from(json).unwrap();
             assert_eq!(timings.data.len(), 1);
             let timing = timings.data.first().unwrap();
             assert_eq!(timing.day, day!(1));
-            assert_eq!(timing.part_1, Some("1ms".to_string()));
+            assert_eq!(
+                timing.part_1,
+                Some(PartTiming {
+                    nanos: 1_000_000_f64,
+                    samples: 0,
+                    spread_nanos: 0_f64,
+                })
+            );
             assert_eq!(timing.part_2, None);
-            assert_eq!(timing.total_nanos, 1_000_000_000_f64);
+            assert_eq!(timing.total_nanos(), 1_000_000_f64);
         }
 
         #[test]
@@ -235,6 +457,23 @@ mod tests {
             let json = r#"{ "data": [] }"#.to_string();
             let timings = Timings::try_from(json).unwrap();
             assert_eq!(timings.data.len(), 0);
+            assert_eq!(format!("{:.2}", timings.total_millis()), "0.00");
+        }
+
+        #[test]
+        #[should_panic]
+        fn panics_for_newer_versions() {
+            let json = r#"{ "version": 99, "data": [] }"#.to_string();
+            Timings::try_from(json).unwrap();
+        }
+
+        #[test]
+        #[should_panic]
+        fn panics_for_malformed_parts() {
+            let json =
+                r#"{ "version": 2, "data": [{ "day": "01", "part_1": "1ms", "part_2": null }] }"#
+                    .to_string();
+            Timings::try_from(json).unwrap();
         }
 
         #[test]
@@ -254,9 +493,61 @@ mod tests {
 
     mod serialization {
         use super::get_mock_timings;
+        use crate::day;
+        use crate::template::machine::MachineInfo;
+        use crate::template::timings::{Timings, SCHEMA_VERSION};
         use std::collections::HashMap;
         use tinyjson::JsonValue;
 
+        #[test]
+        fn round_trips_timings() {
+            let timings = get_mock_timings();
+            let json = JsonValue::from(timings.clone()).stringify().unwrap();
+            assert!(json.contains(&format!(r#""version":{SCHEMA_VERSION}"#)));
+
+            let parsed = Timings::try_from(json).unwrap();
+            assert_eq!(parsed.data.len(), 3);
+            for (parsed, original) in parsed.data.iter().zip(&timings.data) {
+                assert_eq!(parsed.day, original.day);
+                assert_eq!(parsed.part_1, original.part_1);
+                assert_eq!(parsed.part_2, original.part_2);
+            }
+            assert_eq!(parsed.total_millis(), 140_f64);
+            assert_eq!(parsed.machine, None);
+        }
+
+        #[test]
+        fn round_trips_single_threaded_timings() {
+            let mut timings = get_mock_timings();
+            timings.single_threaded = vec![timings.data[1].clone()];
+            let json = JsonValue::from(timings.clone()).stringify().unwrap();
+
+            let parsed = Timings::try_from(json).unwrap();
+            assert_eq!(parsed.single_threaded.len(), 1);
+            assert_eq!(parsed.single_threaded(day!(2)), timings.data.get(1));
+            assert_eq!(parsed.single_threaded(day!(1)), None);
+
+            // single-threaded timings are merged by day, like the others
+            let merged = get_mock_timings().merge(&parsed);
+            assert_eq!(merged.single_threaded.len(), 1);
+            let merged = parsed.merge(&get_mock_timings());
+            assert_eq!(merged.single_threaded.len(), 1);
+        }
+
+        #[test]
+        fn round_trips_machine() {
+            let mut timings = get_mock_timings();
+            timings.machine = Some(MachineInfo::capture(true, None));
+            let json = JsonValue::from(timings.clone()).stringify().unwrap();
+
+            let parsed = Timings::try_from(json).unwrap();
+            assert_eq!(parsed.machine, timings.machine);
+
+            // merging in timings without a machine keeps the recorded one
+            let merged = parsed.merge(&get_mock_timings());
+            assert_eq!(merged.machine, timings.machine);
+        }
+
         #[test]
         fn serializes_timings() {
             let timings = get_mock_timings();
@@ -276,6 +567,7 @@ mod tests {
     }
 
     mod is_day_complete {
+        use super::part;
         use crate::{
             day,
             template::timings::{Timing, Timings},
@@ -286,13 +578,14 @@ mod tests {
             let timings = Timings {
                 data: vec![Timing {
                     day: day!(1),
-                    part_1: Some("1ms".into()),
-                    part_2: Some("2ms".into()),
-                    total_nanos: 3_000_000_000_f64,
+                    part_1: Some(part(1.0)),
+                    part_2: Some(part(2.0)),
                 }],
+                machine: None,
+                single_threaded: vec![],
             };
 
-            assert_eq!(timings.is_day_complete(&day!(1)), true);
+            assert_eq!(timings.is_day_complete(day!(1)), true);
         }
 
         #[test]
@@ -300,13 +593,14 @@ mod tests {
             let timings = Timings {
                 data: vec![Timing {
                     day: day!(1),
-                    part_1: Some("1ms".into()),
+                    part_1: Some(part(1.0)),
                     part_2: None,
-                    total_nanos: 1_000_000_000_f64,
                 }],
+                machine: None,
+                single_threaded: vec![],
             };
 
-            assert_eq!(timings.is_day_complete(&day!(1)), false);
+            assert_eq!(timings.is_day_complete(day!(1)), false);
         }
 
         #[test]
@@ -316,11 +610,12 @@ mod tests {
                     day: day!(1),
                     part_1: None,
                     part_2: None,
-                    total_nanos: 0.0,
                 }],
+                machine: None,
+                single_threaded: vec![],
             };
 
-            assert_eq!(timings.is_day_complete(&day!(1)), false);
+            assert_eq!(timings.is_day_complete(day!(1)), false);
         }
     }
 
@@ -340,8 +635,9 @@ mod tests {
                     day: day!(3),
                     part_1: None,
                     part_2: None,
-                    total_nanos: 0_f64,
                 }],
+                machine: None,
+                single_threaded: vec![],
             };
             let merged = timings.merge(&other);
             assert_eq!(merged.data.len(), 4);
@@ -360,15 +656,16 @@ mod tests {
                     day: day!(2),
                     part_1: None,
                     part_2: None,
-                    total_nanos: 0_f64,
                 }],
+                machine: None,
+                single_threaded: vec![],
             };
             let merged = timings.merge(&other);
 
             assert_eq!(merged.data.len(), 3);
             assert_eq!(merged.data[0].day, day!(1));
             assert_eq!(merged.data[1].day, day!(2));
-            assert_eq!(merged.data[1].total_nanos, 0_f64);
+            assert_eq!(merged.data[1].total_nanos(), 0_f64);
             assert_eq!(merged.data[2].day, day!(4));
         }
 